@@ -1 +1,5 @@
-cat ~\git\canvas-fuzzy-finder\buf | fzf | Out-File -FilePath ~\git\canvas-fuzzy-finder\title-url-name.txt
\ No newline at end of file
+$buf = $args[0]
+$out = $args[1]
+$finder = $args[2]
+$rest = @($args | Select-Object -Skip 3)
+cat $buf | & $finder @rest | Out-File -FilePath $out -Encoding utf8NoBOM
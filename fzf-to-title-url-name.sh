@@ -1 +1,5 @@
-cat ~/git/canvas-fuzzy-finder/buf | fzf > ~/git/canvas-fuzzy-finder/title-url-name.txt
\ No newline at end of file
+buf="$1"
+out="$2"
+finder="$3"
+shift 3
+cat "$buf" | "$finder" "$@" > "$out"
@@ -0,0 +1,182 @@
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::entry::Entry;
+
+// the fetched entries, along with a description of what was fetched so that
+// changing courses or content types doesn't serve a stale list
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EntriesCache {
+    pub key: String,
+    pub entries: Vec<Entry>,
+    // caches written before this was kept just don't say how old they are
+    #[serde(default)]
+    pub fetched_at: Option<DateTime<Utc>>,
+}
+
+// how far a listing got before a page of it failed, so the next run can pick
+// up from the page it stopped at rather than starting over
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Cursor {
+    // the page that failed
+    pub next: String,
+    // everything from the pages before it
+    pub items: Vec<serde_json::Value>,
+}
+
+// how long a cursor is resumed from before it's too old to trust
+pub const CURSOR_TTL: Duration = Duration::from_secs(60 * 60 * 24);
+
+// how old the entries being shown are
+#[derive(Debug, Clone, Copy)]
+pub enum Age {
+    // fetched during this run
+    Fresh,
+    FetchedAt(DateTime<Utc>),
+    // from a cache written before it kept track
+    Unknown,
+}
+
+impl Age {
+    pub fn of(cache: &EntriesCache) -> Self {
+        cache.fetched_at.map_or(Age::Unknown, Age::FetchedAt)
+    }
+}
+
+// how long anything cached is used for before it's fetched again, unless
+// `[cache_ttl]` says otherwise
+const TTL: Duration = Duration::from_secs(300);
+
+// the listings of a course that are each cached on their own
+#[derive(Debug, Clone, Copy)]
+pub enum List {
+    Modules,
+    Pages,
+    Assignments,
+    Announcements,
+    // not entries, but the names of assignment groups
+    AssignmentGroups,
+    Syllabus,
+}
+
+impl List {
+    pub fn name(&self) -> &'static str {
+        match self {
+            List::Modules => "modules",
+            List::Pages => "pages",
+            List::Assignments => "assignments",
+            List::Announcements => "announcements",
+            List::AssignmentGroups => "assignment-groups",
+            List::Syllabus => "syllabus",
+        }
+    }
+}
+
+// how long each listing is cached for, since e.g. announcements change far
+// more often than modules do
+#[derive(Debug, Clone, Copy)]
+pub struct Ttls {
+    pub modules: Duration,
+    pub pages: Duration,
+    pub assignments: Duration,
+    pub announcements: Duration,
+}
+
+impl Default for Ttls {
+    fn default() -> Self {
+        Self {
+            modules: TTL,
+            pages: TTL,
+            assignments: TTL,
+            announcements: TTL,
+        }
+    }
+}
+
+impl Ttls {
+    pub fn get(&self, list: List) -> Duration {
+        match list {
+            List::Modules => self.modules,
+            List::Pages | List::Syllabus => self.pages,
+            List::Assignments | List::AssignmentGroups => self.assignments,
+            List::Announcements => self.announcements,
+        }
+    }
+
+    // how long the combined entries cache can be used for without any
+    // listing in it going stale
+    pub fn shortest(&self) -> Duration {
+        [
+            self.modules,
+            self.pages,
+            self.assignments,
+            self.announcements,
+        ]
+        .into_iter()
+        .min()
+        .unwrap()
+    }
+}
+
+// `90s`, `2m`, `1h`, or `1d`
+pub fn parse_ttl(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (amount, unit) = s.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| anyhow::anyhow!("invalid ttl `{}`, expected e.g. `90s`, `2m`, or `1h`", s))?;
+    let secs = match unit {
+        "s" | "" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        _ => anyhow::bail!("invalid ttl `{}`, expected e.g. `90s`, `2m`, or `1h`", s),
+    };
+    Ok(Duration::from_secs(amount * secs))
+}
+
+// whether a cache file exists and was written within `ttl`
+pub fn is_fresh(path: &Path, ttl: Duration) -> bool {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < ttl)
+}
+
+// reads a cached file as text, dropping the byte order mark an editor on
+// windows may have added, which would otherwise end up in the first title
+pub fn read_text(path: &Path) -> std::io::Result<String> {
+    let text = std::fs::read_to_string(path)?;
+    Ok(match text.strip_prefix('\u{feff}') {
+        Some(rest) => rest.to_string(),
+        None => text,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_a_byte_order_mark() {
+        let path = std::env::temp_dir().join(format!("cff-bom-{}.txt", std::process::id()));
+        std::fs::write(
+            &path,
+            "\u{feff}Homework 1 || https://canvas.test/a/1 || CS 101\n",
+        )
+        .unwrap();
+        let text = read_text(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let text = text.unwrap();
+        assert!(text.starts_with("Homework 1"));
+        let entry = crate::entry::parse_selection(text.trim(), crate::entry::SEPARATOR).unwrap();
+        assert_eq!(entry.title, "Homework 1");
+    }
+}
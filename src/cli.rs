@@ -0,0 +1,296 @@
+use clap::{Parser, Subcommand, ValueEnum};
+
+/// Fuzzy find any module item across all your canvas courses
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Command fzf runs to preview the highlighted line, `{}` is replaced
+    /// with the line. Defaults to this program's `preview` subcommand, given
+    /// the same network flags as this run
+    #[arg(long, value_name = "TEMPLATE")]
+    pub preview_cmd: Option<String>,
+
+    /// Fetch the previews of the first N pages in the background while the
+    /// finder is open
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub prefetch_previews: usize,
+
+    /// Mark entries you've opened in the last week with how long ago it was
+    #[arg(long)]
+    pub show_recency: bool,
+
+    /// Only search the course with this id, name, code, or nickname
+    #[arg(long)]
+    pub course: Option<String>,
+
+    /// Only search the courses favorited in canvas, when courses aren't set
+    /// with `COURSE_IDS`
+    #[arg(long)]
+    pub favorites: bool,
+
+    /// Start fetching the courses favorited in Canvas before the rest, so
+    /// they're ready first
+    #[arg(long)]
+    pub favorites_first: bool,
+
+    /// Open the entry with exactly this title (ignoring case) instead of
+    /// picking one in the finder
+    #[arg(long, value_name = "TITLE")]
+    pub open_title: Option<String>,
+
+    /// Also list every page in each course, not just the ones in modules
+    #[arg(long)]
+    pub include_pages: bool,
+
+    /// Also list every assignment and quiz in each course
+    #[arg(long)]
+    pub include_assignments: bool,
+
+    /// Also list every announcement in each course
+    #[arg(long)]
+    pub include_announcements: bool,
+
+    /// List each course's announcements as one entry, titled with the latest
+    /// and how many are unread, that opens the course's announcements page.
+    /// Turns on `--include-announcements`
+    #[arg(long)]
+    pub announcements_digest: bool,
+
+    /// Also list each course's syllabus, for courses that have written one
+    #[arg(long)]
+    pub include_syllabus: bool,
+
+    /// Also list each course's grades page
+    #[arg(long)]
+    pub include_grades_page: bool,
+
+    /// How entries are ordered in the finder
+    #[arg(long, value_enum, default_value_t = Sort::Course)]
+    pub sort: Sort,
+
+    /// Move each course's front page to the top of its entries
+    #[arg(long)]
+    pub front_page_first: bool,
+
+    /// Don't list a course's pages when its modules are empty or disabled
+    #[arg(long)]
+    pub no_pages_fallback: bool,
+
+    /// Print the selected line and everything known about its entry instead
+    /// of opening it
+    #[arg(long)]
+    pub no_open: bool,
+
+    /// Read the token from stdin instead of `TOKEN`. The finder is still
+    /// handed it in `TOKEN`, for its previews, so it can be seen in the
+    /// finder's environment. Can't be used with anything that picks from
+    /// stdin, like `--safe`
+    #[arg(long, conflicts_with = "safe")]
+    pub token_stdin: bool,
+
+    /// Give up on a request after this many seconds
+    #[arg(long, value_name = "SECS")]
+    pub timeout: Option<u64>,
+
+    /// Send every request through this proxy, e.g. `http://proxy:8080`
+    #[arg(long, value_name = "URL")]
+    pub proxy: Option<String>,
+
+    /// Also trust the certificates in this pem file
+    #[arg(long, value_name = "FILE")]
+    pub ca_bundle: Option<std::path::PathBuf>,
+
+    /// Don't check certificates at all. Only for debugging, this exposes
+    /// your token to anyone in the middle
+    #[arg(long)]
+    pub accept_invalid_certs: bool,
+
+    /// Send an extra header with every request, as `Name: value`
+    #[arg(long = "header", value_name = "HEADER")]
+    pub headers: Vec<String>,
+
+    /// Add a query parameter to every request to the REST API, as
+    /// `key=value`, passed on as is e.g. for an `include[]=` that isn't
+    /// otherwise asked for
+    #[arg(long = "query-param", value_name = "KEY=VALUE")]
+    pub query_params: Vec<String>,
+
+    /// Don't touch the network, picking from the last fetched entries
+    /// however old they are and opening downloaded copies where there are
+    /// any
+    #[arg(long)]
+    pub offline: bool,
+
+    /// Download the selected entry if it's a file, and open the local copy
+    #[arg(long, conflicts_with_all = ["offline", "safe"])]
+    pub download: bool,
+
+    /// Run this finder instead of `fzf` on PATH, e.g. a full path to fzf or
+    /// a drop-in like `sk`
+    #[arg(long, value_name = "BIN")]
+    pub finder_bin: Option<String>,
+
+    /// Cluster entries, e.g. assignments by assignment group (which needs
+    /// `--include-assignments` to list them all)
+    #[arg(long, value_enum)]
+    pub group_by: Option<GroupBy>,
+
+    /// Try to fetch every course within this many seconds, fetching more
+    /// courses at once if they're coming in too slowly
+    #[arg(long, value_name = "SECS")]
+    pub time_budget: Option<u64>,
+
+    /// Report how long fetching courses took and how many were fetched at
+    /// once
+    #[arg(long)]
+    pub stats: bool,
+
+    /// Pick several entries at once with tab, opening each of them
+    #[arg(long)]
+    pub multi: bool,
+
+    /// Open what's picked in a new browser window rather than as tabs in the
+    /// last one, with any others from `--multi` as tabs in it (needs
+    /// `--browser`)
+    #[arg(long)]
+    pub new_window: bool,
+
+    /// Save every response from canvas into this directory, with the token
+    /// blanked out, for `--replay` to play back
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["replay", "offline"])]
+    pub record: Option<std::path::PathBuf>,
+
+    /// Answer every request from what `--record` saved into this directory
+    /// instead of asking canvas
+    #[arg(long, value_name = "DIR", conflicts_with = "offline")]
+    pub replay: Option<std::path::PathBuf>,
+
+    /// What to do when the finder can't be run, e.g. without a terminal to
+    /// open it in or with fzf not installed
+    #[arg(long, value_enum, default_value_t = OnNoFinder::Error)]
+    pub on_no_finder: OnNoFinder,
+
+    /// Print a line saying what was opened, its course, kind, and when it's
+    /// due, for a record in the terminal's scrollback
+    #[arg(long)]
+    pub summary: bool,
+
+    /// Take tracking parameters like `utm_source` and `session_token` (or
+    /// whatever `strip_params` in config.toml lists) off links before opening
+    /// them
+    #[arg(long)]
+    pub flatten_external: bool,
+
+    /// Open the finder straight away on whatever was fetched last, adding
+    /// entries to it as they're fetched again. Those go at the end of the
+    /// list, without being sorted or grouped with the rest
+    #[arg(
+        long,
+        conflicts_with_all = ["offline", "open_title", "safe", "only_new", "max_entries"]
+    )]
+    pub stream: bool,
+
+    /// Show at most this many entries, sharing them evenly between courses
+    /// with `--sort course` or keeping the most recent with `--sort recent`
+    #[arg(long, value_name = "N")]
+    pub max_entries: Option<usize>,
+
+    /// Use plain ascii for assignment status icons
+    #[arg(long)]
+    pub ascii_icons: bool,
+
+    /// Open links in this browser instead of the system default
+    #[arg(long, value_enum)]
+    pub browser: Option<crate::opener::Browser>,
+
+    /// Open links in this profile of `--browser`, e.g. `Profile 1` for
+    /// chrome or a profile name for firefox
+    #[arg(long)]
+    pub profile: Option<String>,
+
+    /// Only show entries that were added or changed the last time the cache
+    /// was refetched
+    #[arg(long)]
+    pub only_new: bool,
+
+    /// Fetch courses with a graphql query each instead of several rest
+    /// calls, falling back to rest for any course that fails
+    #[arg(long, value_enum)]
+    pub backend: Option<crate::graphql::Backend>,
+
+    /// Only show titles in the finder, tagged with what kind of entry they
+    /// are, for narrow terminals. The url and course are still matched
+    /// against
+    #[arg(long)]
+    pub compact: bool,
+
+    /// Cut titles in the finder down to this many characters. The whole
+    /// title is still matched against
+    #[arg(long, value_name = "N")]
+    pub truncate: Option<usize>,
+
+    /// Never run another program, picking from a built in list on the
+    /// terminal and printing the selection instead of opening it
+    #[arg(long, conflicts_with = "browser")]
+    pub safe: bool,
+
+    /// Check the selected link still exists before opening it, offering to
+    /// refresh the cache if it doesn't
+    #[arg(long)]
+    pub verify_before_open: bool,
+
+    /// Run everything on a single thread instead of one worker per core
+    #[arg(long)]
+    pub single_thread: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Print a preview of a line from the finder, e.g. the text of a page
+    Preview {
+        /// A `title || url || course` line
+        line: String,
+    },
+    /// Print the id, name, code, and nickname of every course that's searched
+    ListCourses,
+    /// Refetch everything, including page previews, e.g. from a scheduled
+    /// job so later runs are instant
+    Warm,
+    /// Delete history and other saved state, but not the cache
+    ResetState,
+    /// Delete cached listings and previews of courses that are no longer
+    /// searched
+    PruneCache,
+    /// Check that the config, token, canvas, finder, and cache are all
+    /// usable, without changing anything
+    Doctor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OnNoFinder {
+    /// Stop, saying what's missing
+    Error,
+    /// Print every entry instead
+    Print,
+    /// Pick from a numbered list in this terminal instead, like `--safe`
+    /// but still opening what's picked
+    Inline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+    /// Each course's assignments under their assignment group, like the
+    /// course's assignments page
+    AssignmentGroup,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Sort {
+    /// Grouped by course, in the order canvas lists them
+    Course,
+    /// Most recently updated, posted, or due first
+    Recent,
+}
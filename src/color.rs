@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+
+use crate::Course;
+
+// what courses without a color of their own are given, picked between by id
+const PALETTE: [u8; 6] = [31, 32, 33, 34, 35, 36];
+
+// the steps of each channel in the 256 color cube
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+// the escape code parameters each course's label is colored with, keyed by
+// label since that's all an entry knows about its course
+pub fn course_colors(courses: &[Course]) -> HashMap<String, String> {
+    courses
+        .iter()
+        .map(|course| {
+            let color = match course.color {
+                Some(rgb) => format!("38;5;{}", nearest_256(rgb)),
+                None => PALETTE[(course.id % PALETTE.len() as u64) as usize].to_string(),
+            };
+            (course.label().to_string(), color)
+        })
+        .collect()
+}
+
+// `#rrggbb` or `#rgb`, as canvas hands out
+pub fn parse_hex(hex: &str) -> Option<(u8, u8, u8)> {
+    let hex = hex.strip_prefix('#')?;
+    let channel = |s: &str| u8::from_str_radix(s, 16).ok();
+    match hex.len() {
+        6 => Some((
+            channel(&hex[0..2])?,
+            channel(&hex[2..4])?,
+            channel(&hex[4..6])?,
+        )),
+        3 => Some((
+            channel(&hex[0..1])? * 17,
+            channel(&hex[1..2])? * 17,
+            channel(&hex[2..3])? * 17,
+        )),
+        _ => None,
+    }
+}
+
+// the closest color in the 256 color cube, which nearly every terminal fzf
+// runs in supports
+fn nearest_256((r, g, b): (u8, u8, u8)) -> u8 {
+    let level = |c: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|&i| (CUBE_LEVELS[i] as i16 - c as i16).abs())
+            .unwrap() as u8
+    };
+    16 + 36 * level(r) + 6 * level(g) + level(b)
+}
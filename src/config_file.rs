@@ -0,0 +1,101 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::entry::EntryKind;
+use crate::graphql::Backend;
+use crate::opener::Browser;
+
+// `config.toml` in the config dir, for settings that don't fit in a `.env`.
+// every setting is optional, and flags and environment variables win over it
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    // regexes for titles that should never show up, like `^Attendance`
+    pub hide_patterns: Vec<String>,
+    // the finder to run instead of `fzf` on `PATH`
+    pub finder_bin: Option<String>,
+    pub status_icons: StatusIconsSection,
+    pub browser: BrowserSection,
+    pub backend: Option<Backend>,
+    pub cache_ttl: CacheTtlSection,
+    pub network: NetworkSection,
+    // where each kind of entry goes within a course, lowest first, e.g.
+    // `announcement = 1`
+    pub kind_priority: HashMap<EntryKind, i32>,
+    // query parameters `--flatten-external` strips, e.g. `utm_*`
+    pub strip_params: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkSection {
+    // seconds before a request is given up on
+    pub timeout: Option<u64>,
+    pub proxy: Option<String>,
+    // a pem file of extra certificates to trust
+    pub ca_bundle: Option<PathBuf>,
+    pub accept_invalid_certs: Option<bool>,
+    pub extra_headers: BTreeMap<String, String>,
+}
+
+// icons for where an assignment's submission stands, any of which can be left
+// out to keep the built in one
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatusIconsSection {
+    // start from plain ascii icons instead of symbols
+    pub ascii: bool,
+    pub submitted: Option<String>,
+    pub missing: Option<String>,
+    pub late: Option<String>,
+    pub upcoming: Option<String>,
+}
+
+// which browser, and profile of it, links are opened in. anything in a
+// `[browser.linux]`, `[browser.macos]`, or `[browser.windows]` table is used
+// over the rest on that os
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BrowserSection {
+    #[serde(flatten)]
+    pub defaults: BrowserSettings,
+    pub linux: Option<BrowserSettings>,
+    pub macos: Option<BrowserSettings>,
+    pub windows: Option<BrowserSettings>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct BrowserSettings {
+    pub name: Option<Browser>,
+    pub command: Option<String>,
+    pub profile: Option<String>,
+    // a devtools port to open links through, so they go to one running
+    // browser rather than starting another
+    pub remote_port: Option<u16>,
+}
+
+// how long each kind of listing is cached for, e.g. `"2m"` or `"1h"`,
+// defaulting to 5 minutes
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheTtlSection {
+    pub modules: Option<String>,
+    pub pages: Option<String>,
+    pub assignments: Option<String>,
+    pub announcements: Option<String>,
+}
+
+impl ConfigFile {
+    // a missing file is the same as an empty one
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(s) => toml::from_str(&s).with_context(|| format!("invalid {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("couldn't read {}", path.display())),
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+// where each downloaded file's url was saved to, so that `--offline` can open
+// the local copy instead
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Downloads {
+    files: HashMap<String, PathBuf>,
+    // what was downloaded since loading, to add to whatever's been saved
+    // since by another run
+    #[serde(skip)]
+    added: HashMap<String, PathBuf>,
+}
+
+impl Downloads {
+    // a missing or unreadable file just means nothing has been downloaded
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        crate::paths::update_state_atomic(path, |current| {
+            let mut saved: Downloads = current
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default();
+            saved.files.extend(self.added.clone());
+            Ok(serde_json::to_string(&saved)?)
+        })
+    }
+
+    pub fn record(&mut self, url: &str, local: PathBuf) {
+        self.files.insert(url.to_string(), local.clone());
+        self.added.insert(url.to_string(), local);
+    }
+
+    // the local copy of a url, as long as it hasn't been deleted since
+    pub fn get(&self, url: &str) -> Option<&Path> {
+        self.files
+            .get(url)
+            .map(PathBuf::as_path)
+            .filter(|path| path.exists())
+    }
+}
@@ -0,0 +1,750 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Local, Utc};
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::history::History;
+
+// what fields of a rendered line are joined with
+pub const SEPARATOR: &str = " || ";
+
+// how many days after opening an entry it's still marked with `--show-recency`
+const RECENCY_WINDOW_DAYS: i64 = 7;
+
+// how soon something has to be due to be highlighted as urgent or upcoming
+const URGENT_HOURS: i64 = 24;
+const SOON_DAYS: i64 = 7;
+
+// with `--compact`, everything after the title is pushed this many columns to
+// the right, where it's off screen but can still be matched against. it's
+// made of no-break spaces so it can be told apart from spaces in the title
+const COMPACT_PADDING: usize = 400;
+const PAD: char = '\u{a0}';
+
+// a single selectable line in the finder
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Entry {
+    pub title: String,
+    pub url: String,
+    pub course: String,
+    #[serde(default)]
+    pub kind: EntryKind,
+    pub due_at: Option<DateTime<Utc>>,
+    // when it was last updated or posted, for `--sort recent`
+    #[serde(default)]
+    pub recent_at: Option<DateTime<Utc>>,
+    // whether this is the page a course lands on
+    #[serde(default)]
+    pub front_page: bool,
+    // the page's url slug, for entries that are wiki pages
+    #[serde(default)]
+    pub page_slug: Option<String>,
+    // the id of the assignment, quiz, etc. the entry is
+    #[serde(default)]
+    pub content_id: Option<u64>,
+    // where your submission stands, for assignments and quizzes
+    #[serde(default)]
+    pub submission: Option<Submission>,
+    // which assignment group an assignment is in, and that group's name if
+    // assignments are being grouped
+    #[serde(default)]
+    pub assignment_group_id: Option<u64>,
+    #[serde(default)]
+    pub assignment_group: Option<String>,
+    // whether an announcement hasn't been read yet
+    #[serde(default)]
+    pub unread: bool,
+}
+
+// the parts of a canvas submission that decide its status icon
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Submission {
+    pub submitted: bool,
+    pub late: bool,
+    pub missing: bool,
+}
+
+impl Submission {
+    pub fn from_json(submission: &serde_json::Value) -> Option<Self> {
+        let state = submission["workflow_state"].as_str()?;
+        Some(Self {
+            submitted: matches!(state, "submitted" | "graded" | "pending_review"),
+            late: submission["late"].as_bool().unwrap_or(false),
+            missing: submission["missing"].as_bool().unwrap_or(false),
+        })
+    }
+}
+
+// what's shown in front of an assignment's title for where it stands
+#[derive(Debug, Clone)]
+pub struct StatusIcons {
+    pub submitted: String,
+    pub missing: String,
+    pub late: String,
+    pub upcoming: String,
+}
+
+impl Default for StatusIcons {
+    fn default() -> Self {
+        Self {
+            submitted: "✓".to_string(),
+            missing: "✗".to_string(),
+            late: "⧖".to_string(),
+            upcoming: "○".to_string(),
+        }
+    }
+}
+
+impl StatusIcons {
+    // for terminals and fonts without the symbols
+    pub fn ascii() -> Self {
+        Self {
+            submitted: "+".to_string(),
+            missing: "x".to_string(),
+            late: "~".to_string(),
+            upcoming: "o".to_string(),
+        }
+    }
+
+    // the icon for an entry and the color it's drawn in, if it has a
+    // submission at all
+    fn for_entry(&self, entry: &Entry, now: DateTime<Utc>) -> Option<(&str, u8)> {
+        let submission = entry.submission?;
+        Some(if submission.submitted && submission.late {
+            (&self.late, 33)
+        } else if submission.submitted {
+            (&self.submitted, 32)
+        } else if submission.missing || entry.due_at.is_some_and(|due_at| due_at < now) {
+            (&self.missing, 31)
+        } else {
+            (&self.upcoming, 2)
+        })
+    }
+}
+
+// what an entry links to, following the module item types canvas uses
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    Page,
+    Assignment,
+    Quiz,
+    Discussion,
+    Announcement,
+    File,
+    ExternalUrl,
+    ExternalTool,
+    Syllabus,
+    #[default]
+    Other,
+}
+
+impl EntryKind {
+    // a letter or two to tell kinds apart with when `--compact` hides the rest
+    // of the line
+    fn short(&self) -> &'static str {
+        match self {
+            EntryKind::Page => "p",
+            EntryKind::Assignment => "a",
+            EntryKind::Quiz => "q",
+            EntryKind::Discussion => "d",
+            EntryKind::Announcement => "n",
+            EntryKind::File => "f",
+            EntryKind::ExternalUrl => "l",
+            EntryKind::ExternalTool => "t",
+            EntryKind::Syllabus => "s",
+            EntryKind::Other => "-",
+        }
+    }
+
+    pub fn from_module_item_type(item_type: &str) -> Self {
+        match item_type {
+            "Page" => EntryKind::Page,
+            "Assignment" => EntryKind::Assignment,
+            "Quiz" => EntryKind::Quiz,
+            "Discussion" => EntryKind::Discussion,
+            "File" => EntryKind::File,
+            "ExternalUrl" => EntryKind::ExternalUrl,
+            "ExternalTool" => EntryKind::ExternalTool,
+            _ => EntryKind::Other,
+        }
+    }
+}
+
+impl Entry {
+    // the most recent thing that happened to an entry, preferring when it
+    // was last touched over when it's due
+    pub fn recency(&self) -> Option<DateTime<Utc>> {
+        self.recent_at.or(self.due_at)
+    }
+
+    // whether two entries are the same page, assignment, etc. reached from
+    // different places
+    fn same_content(&self, other: &Entry) -> bool {
+        self.kind == other.kind
+            && ((self.page_slug.is_some() && self.page_slug == other.page_slug)
+                || (self.content_id.is_some() && self.content_id == other.content_id))
+    }
+}
+
+// adds entries from one of a course's listings (pages, assignments, ...) to
+// its module items, folding anything already linked from a module into that
+// module item
+pub fn merge(entries: &mut Vec<Entry>, listed: Vec<Entry>) {
+    for entry in listed {
+        match entries.iter_mut().find(|e| e.same_content(&entry)) {
+            Some(existing) => {
+                existing.front_page |= entry.front_page;
+                existing.due_at = existing.due_at.or(entry.due_at);
+                existing.recent_at = existing.recent_at.or(entry.recent_at);
+                existing.submission = existing.submission.or(entry.submission);
+                existing.assignment_group_id =
+                    existing.assignment_group_id.or(entry.assignment_group_id);
+                existing.page_slug = existing.page_slug.take().or(entry.page_slug);
+            }
+            None => entries.push(entry),
+        }
+    }
+}
+
+// one entry standing in for all of a course's announcements, titled with the
+// latest of them and how many haven't been read, that links to `url`
+pub fn digest_announcements(announcements: Vec<Entry>, url: String) -> Option<Entry> {
+    let unread = announcements.iter().filter(|entry| entry.unread).count();
+    let latest = announcements
+        .into_iter()
+        .max_by_key(|entry| entry.recent_at)?;
+    let title = match unread {
+        0 => latest.title,
+        unread => format!("{} ({} new)", latest.title, unread),
+    };
+    Some(Entry {
+        title,
+        url,
+        unread: unread > 0,
+        content_id: None,
+        ..latest
+    })
+}
+
+// the entries that weren't in `previous`, or were but have since been renamed,
+// rescheduled, or updated
+pub fn new_since(entries: Vec<Entry>, previous: &[Entry]) -> Vec<Entry> {
+    let previous: HashMap<&str, &Entry> = previous
+        .iter()
+        .map(|entry| (entry.url.as_str(), entry))
+        .collect();
+    entries
+        .into_iter()
+        .filter(|entry| match previous.get(entry.url.as_str()) {
+            Some(old) => {
+                old.title != entry.title
+                    || old.due_at != entry.due_at
+                    || old.recent_at != entry.recent_at
+            }
+            None => true,
+        })
+        .collect()
+}
+
+// gathers each course's assignments under their assignment group, after
+// everything that isn't in one, in the order the groups first show up.
+// courses themselves stay in the same order
+pub fn group_by_assignment_group(entries: &mut [Entry]) {
+    let mut course_order = HashMap::new();
+    let mut group_order = HashMap::new();
+    for entry in entries.iter() {
+        let next = course_order.len();
+        course_order.entry(entry.course.clone()).or_insert(next);
+        if let Some(group) = &entry.assignment_group {
+            let next = group_order.len();
+            group_order
+                .entry((entry.course.clone(), group.clone()))
+                .or_insert(next);
+        }
+    }
+    entries.sort_by_key(|entry| {
+        let group = entry
+            .assignment_group
+            .as_ref()
+            .map(|group| group_order[&(entry.course.clone(), group.clone())]);
+        (course_order[&entry.course], group)
+    });
+}
+
+// most recently updated, posted, or due first, with undated entries last
+pub fn sort_by_recency(entries: &mut [Entry]) {
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.recency()));
+}
+
+// trims entries down to `max`, keeping their order. with `by_course` every
+// course gets an even share (any share a small course doesn't use goes to the
+// others), otherwise the first `max` in the current sort are kept. returns how
+// many were dropped
+pub fn cap(entries: &mut Vec<Entry>, max: usize, by_course: bool) -> usize {
+    let total = entries.len();
+    if total <= max {
+        return 0;
+    }
+    if !by_course {
+        entries.truncate(max);
+        return total - max;
+    }
+
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for entry in entries.iter() {
+        match counts
+            .iter_mut()
+            .find(|(course, _)| *course == entry.course)
+        {
+            Some((_, count)) => *count += 1,
+            None => counts.push((&entry.course, 1)),
+        }
+    }
+
+    // smallest courses first, so what they leave over is shared by the rest
+    counts.sort_by_key(|&(_, count)| count);
+    let mut quotas: HashMap<String, usize> = HashMap::new();
+    let mut remaining = max;
+    for (i, &(course, count)) in counts.iter().enumerate() {
+        let courses_left = counts.len() - i;
+        let share = remaining.div_ceil(courses_left);
+        let quota = count.min(share);
+        quotas.insert(course.to_string(), quota);
+        remaining -= quota;
+    }
+
+    entries.retain(|entry| {
+        let quota = quotas.get_mut(&entry.course).unwrap();
+        let keep = *quota > 0;
+        *quota = quota.saturating_sub(1);
+        keep
+    });
+    total - entries.len()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateFormat {
+    Relative,
+    Absolute,
+}
+
+impl DateFormat {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "relative" => Ok(DateFormat::Relative),
+            "absolute" => Ok(DateFormat::Absolute),
+            _ => anyhow::bail!(
+                "unknown date format `{}`, expected `relative` or `absolute`",
+                s
+            ),
+        }
+    }
+
+    pub fn format(&self, date: DateTime<Utc>, now: DateTime<Utc>) -> String {
+        match self {
+            DateFormat::Relative => format_relative(date - now),
+            DateFormat::Absolute => date
+                .with_timezone(&Local)
+                .format("%a %b %-d %H:%M")
+                .to_string(),
+        }
+    }
+}
+
+// turns the offset of a date from now into e.g. "2h ago" or "in 3d"
+pub fn format_relative(delta: Duration) -> String {
+    let secs = delta.num_seconds().abs();
+    let amount = if secs < 60 {
+        return "now".to_string();
+    } else if secs < 60 * 60 {
+        format!("{}m", secs / 60)
+    } else if secs < 60 * 60 * 24 {
+        format!("{}h", secs / (60 * 60))
+    } else {
+        format!("{}d", secs / (60 * 60 * 24))
+    };
+
+    if delta < Duration::zero() {
+        format!("{} ago", amount)
+    } else {
+        format!("in {}", amount)
+    }
+}
+
+pub struct RenderOptions<'a> {
+    pub date_format: DateFormat,
+    // set to mark entries opened within the last week
+    pub history: Option<&'a History>,
+    // escape code parameters to color each course's label with
+    pub course_colors: Option<&'a HashMap<String, String>>,
+    pub status_icons: &'a StatusIcons,
+    // whether icons can be colored, which only the finder expects
+    pub color: bool,
+    // whether to highlight what's due soon, in red and yellow with `color`
+    // or with `[!]` and `[.]` tags without
+    pub urgency: bool,
+    // tag every entry with its kind instead of just tools, since `--compact`
+    // only shows the title
+    pub compact: bool,
+    // the most characters of a title that are shown
+    pub truncate: Option<usize>,
+}
+
+// cuts a title down to `max` characters (as a reader would count them, so
+// accents and emoji aren't split), ending in an ellipsis if it was cut
+fn truncate(title: &str, max: usize) -> Option<String> {
+    let graphemes: Vec<&str> = title.graphemes(true).collect();
+    if graphemes.len() <= max {
+        return None;
+    }
+    let mut short = graphemes[..max.saturating_sub(1)].concat();
+    short.push('…');
+    Some(short)
+}
+
+// a title from canvas with its line breaks made spaces, done as entries are
+// built so it stays on one line wherever it's printed, and comes back from
+// the finder as one line
+pub fn single_line(title: &str) -> String {
+    title.replace("\r\n", " ").replace(['\n', '\r'], " ")
+}
+
+// anything from canvas that's put on a line, which can't be allowed to
+// contain the separator or it'd be split into fields of its own. runs of `|`
+// become a single one. line breaks are made spaces again, for entries cached
+// before titles were
+fn normalize_field(field: &str) -> Cow<'_, str> {
+    if !field.contains("||") && !field.contains(['\n', '\r']) {
+        return Cow::Borrowed(field);
+    }
+    let mut normalized = single_line(field);
+    while normalized.contains("||") {
+        normalized = normalized.replace("||", "|");
+    }
+    Cow::Owned(normalized)
+}
+
+// how close an entry is to being due, for highlighting
+fn urgency(entry: &Entry, now: DateTime<Utc>) -> Option<(&'static str, u8)> {
+    // nothing's urgent once it's handed in
+    if entry
+        .submission
+        .is_some_and(|submission| submission.submitted)
+    {
+        return None;
+    }
+    let until_due = entry.due_at? - now;
+    if until_due < Duration::zero() {
+        None
+    } else if until_due < Duration::hours(URGENT_HOURS) {
+        Some(("!", 31))
+    } else if until_due < Duration::days(SOON_DAYS) {
+        Some((".", 33))
+    } else {
+        None
+    }
+}
+
+// renders entries into the `title || url || course` lines the finder reads,
+// with trailing fields for due dates, when announcements were posted, and when
+// an entry was last opened
+pub fn render(entries: &[Entry], options: &RenderOptions) -> Result<String> {
+    let now = Utc::now();
+    let date_format = options.date_format;
+    let mut buf = String::new();
+
+    for entry in entries {
+        if entry.front_page {
+            write!(&mut buf, "[front] ")?;
+        }
+        if let Some(group) = &entry.assignment_group {
+            write!(&mut buf, "[{}] ", normalize_field(group))?;
+        }
+        if options.compact {
+            write!(&mut buf, "[{}] ", entry.kind.short())?;
+        } else if entry.kind == EntryKind::ExternalTool {
+            write!(&mut buf, "[tool] ")?;
+        } else if entry.kind == EntryKind::Syllabus {
+            write!(&mut buf, "[syllabus] ")?;
+        }
+        // as a tag, so it's stripped back off the title like any other
+        match options.status_icons.for_entry(entry, now) {
+            Some((icon, color)) if options.color => {
+                write!(&mut buf, "[\x1b[{}m{}\x1b[0m] ", color, icon)?
+            }
+            Some((icon, _)) => write!(&mut buf, "[{}] ", icon)?,
+            None => {}
+        }
+        let full_title = normalize_field(&entry.title);
+        let short_title = options.truncate.and_then(|max| truncate(&full_title, max));
+        let title = short_title.as_deref().unwrap_or(&full_title);
+        match urgency(entry, now).filter(|_| options.urgency) {
+            Some((_, color)) if options.color => {
+                write!(&mut buf, "\x1b[{}m{}\x1b[0m", color, title)?
+            }
+            Some((tag, _)) => write!(&mut buf, "[{}] {}", tag, title)?,
+            None => write!(&mut buf, "{}", title)?,
+        }
+        if options.compact {
+            buf.extend(std::iter::repeat_n(PAD, COMPACT_PADDING));
+        }
+        write!(&mut buf, "{SEPARATOR}{}{SEPARATOR}", entry.url)?;
+        let course = normalize_field(&entry.course);
+        match options
+            .course_colors
+            .and_then(|colors| colors.get(&entry.course))
+        {
+            Some(color) => write!(&mut buf, "\x1b[{}m{}\x1b[0m", color, course)?,
+            None => write!(&mut buf, "{}", course)?,
+        }
+        if let Some(due_at) = entry.due_at {
+            write!(
+                &mut buf,
+                "{SEPARATOR}due {}",
+                date_format.format(due_at, now)
+            )?;
+        }
+        if let (EntryKind::Announcement, Some(posted_at)) = (entry.kind, entry.recent_at) {
+            write!(
+                &mut buf,
+                "{SEPARATOR}posted {}",
+                date_format.format(posted_at, now)
+            )?;
+        }
+        if let Some(visit) = options.history.and_then(|history| history.get(&entry.url)) {
+            if now - visit.last_opened < Duration::days(RECENCY_WINDOW_DAYS) {
+                write!(
+                    &mut buf,
+                    "{SEPARATOR}opened {}",
+                    format_relative(visit.last_opened - now)
+                )?;
+            }
+        }
+        // the whole title goes at the end, where it's off screen but can
+        // still be matched against
+        if short_title.is_some() {
+            write!(&mut buf, "{SEPARATOR}{}", full_title)?;
+        }
+        writeln!(&mut buf)?;
+    }
+
+    Ok(buf)
+}
+
+// a line as it'd be shown without `--compact` pushing most of it off screen
+pub fn unpad(line: &str) -> Cow<'_, str> {
+    let padding: String = std::iter::repeat_n(PAD, COMPACT_PADDING).collect();
+    match line.contains(&padding) {
+        true => Cow::Owned(line.replace(&padding, "")),
+        false => Cow::Borrowed(line),
+    }
+}
+
+// gets an entry back out of a line the finder printed, however many fields were
+// rendered around it. the url is whichever field looks like one, the title is
+// the field before it (minus any `[tag]` prefixes), and the course the one after
+pub fn parse_selection(line: &str, sep: &str) -> Option<Entry> {
+    let line = strip_ansi(line);
+    let fields: Vec<&str> = line.split(sep).map(str::trim).collect();
+
+    let url_index = fields
+        .iter()
+        .position(|field| field.starts_with("https://") || field.starts_with("http://"))?;
+
+    let title = match url_index {
+        0 => "",
+        i => strip_tags(fields[i - 1]),
+    };
+
+    Some(Entry {
+        title: title.to_string(),
+        url: fields[url_index].to_string(),
+        course: fields.get(url_index + 1).unwrap_or(&"").to_string(),
+        ..Default::default()
+    })
+}
+
+// every entry with exactly this title, ignoring case
+pub fn find_by_title<'a>(entries: &'a [Entry], title: &str) -> Vec<&'a Entry> {
+    let title = title.to_lowercase();
+    entries
+        .iter()
+        .filter(|entry| entry.title.to_lowercase() == title)
+        .collect()
+}
+
+// orders each course's entries by their kind's priority, lowest first, so
+// kinds given the same priority stay interleaved as they were. kinds without
+// one go last, and the courses themselves stay in the same order
+pub fn sort_by_kind_priority(entries: &mut [Entry], priorities: &HashMap<EntryKind, i32>) {
+    let mut course_order = HashMap::new();
+    for entry in entries.iter() {
+        let next = course_order.len();
+        course_order.entry(entry.course.clone()).or_insert(next);
+    }
+    entries.sort_by_key(|entry| {
+        (
+            course_order[&entry.course],
+            priorities.get(&entry.kind).copied().unwrap_or(i32::MAX),
+        )
+    });
+}
+
+// moves each course's front page to the top of that course's entries, leaving
+// the courses themselves and everything else in the same order
+pub fn front_pages_first(entries: &mut [Entry]) {
+    let mut course_order = HashMap::new();
+    for entry in entries.iter() {
+        let next = course_order.len();
+        course_order.entry(entry.course.clone()).or_insert(next);
+    }
+    entries.sort_by_key(|entry| (course_order[&entry.course], !entry.front_page));
+}
+
+// removes escape sequences like `\x1b[31m` that color a line
+fn strip_ansi(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        // skip the `[`, any parameters, and the final letter
+        if chars.next() == Some('[') {
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        }
+    }
+
+    out
+}
+
+// drops leading `[page] `-style tags off a title
+fn strip_tags(mut title: &str) -> &str {
+    while let Some(rest) = title.strip_prefix('[') {
+        match rest.split_once(']') {
+            Some((_, rest)) => title = rest.trim_start(),
+            None => break,
+        }
+    }
+    title
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_plain_selection() {
+        let entry =
+            parse_selection("Homework 1 || https://canvas.test/a/1 || CS 101", SEPARATOR).unwrap();
+        assert_eq!(entry.title, "Homework 1");
+        assert_eq!(entry.url, "https://canvas.test/a/1");
+        assert_eq!(entry.course, "CS 101");
+    }
+
+    #[test]
+    fn parses_a_colored_selection() {
+        let line = "\x1b[33mHomework 1\x1b[0m || https://canvas.test/a/1 || \x1b[32mCS 101\x1b[0m || due in 2d";
+        let entry = parse_selection(line, SEPARATOR).unwrap();
+        assert_eq!(entry.title, "Homework 1");
+        assert_eq!(entry.url, "https://canvas.test/a/1");
+        assert_eq!(entry.course, "CS 101");
+    }
+
+    #[test]
+    fn parses_a_tagged_selection() {
+        let line = "[front] [\x1b[32m✓\x1b[0m] [!] Home || https://canvas.test/p/home || CS 101";
+        let entry = parse_selection(line, SEPARATOR).unwrap();
+        assert_eq!(entry.title, "Home");
+        assert_eq!(entry.url, "https://canvas.test/p/home");
+    }
+
+    fn render_one(entry: Entry) -> String {
+        let options = RenderOptions {
+            date_format: DateFormat::Relative,
+            history: None,
+            course_colors: None,
+            status_icons: &StatusIcons::default(),
+            color: false,
+            urgency: false,
+            compact: false,
+            truncate: None,
+        };
+        render(&[entry], &options).unwrap()
+    }
+
+    #[test]
+    fn keeps_the_separator_out_of_course_names() {
+        let line = render_one(Entry {
+            title: "Homework || 1".to_string(),
+            url: "https://canvas.test/a/1".to_string(),
+            course: "CS 101 || Section A".to_string(),
+            ..Default::default()
+        });
+        assert_eq!(
+            line,
+            "Homework | 1 || https://canvas.test/a/1 || CS 101 | Section A\n"
+        );
+        let entry = parse_selection(line.trim_end(), SEPARATOR).unwrap();
+        assert_eq!(entry.url, "https://canvas.test/a/1");
+        assert_eq!(entry.course, "CS 101 | Section A");
+    }
+
+    #[test]
+    fn puts_a_title_with_line_breaks_on_one_line() {
+        let title = single_line("Homework\r\n1\nPart\r2");
+        assert_eq!(title, "Homework 1 Part 2");
+
+        let line = render_one(Entry {
+            title,
+            url: "https://canvas.test/a/1".to_string(),
+            course: "CS 101".to_string(),
+            ..Default::default()
+        });
+        assert_eq!(line.lines().count(), 1);
+        let entry = parse_selection(line.trim_end(), SEPARATOR).unwrap();
+        assert_eq!(entry.title, "Homework 1 Part 2");
+        assert_eq!(entry.url, "https://canvas.test/a/1");
+    }
+
+    #[test]
+    fn merging_keeps_the_listed_page_slug() {
+        let mut entries = vec![Entry {
+            url: "https://canvas.test/courses/1/modules/items/9".to_string(),
+            kind: EntryKind::Page,
+            content_id: Some(5),
+            ..Default::default()
+        }];
+        merge(
+            &mut entries,
+            vec![Entry {
+                url: "https://canvas.test/courses/1/pages/home".to_string(),
+                kind: EntryKind::Page,
+                content_id: Some(5),
+                page_slug: Some("home".to_string()),
+                front_page: true,
+                ..Default::default()
+            }],
+        );
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].page_slug.as_deref(), Some("home"));
+        assert!(entries[0].front_page);
+    }
+
+    #[test]
+    fn rejects_a_selection_without_a_url() {
+        assert!(parse_selection("Homework 1 || CS 101", SEPARATOR).is_none());
+        assert!(parse_selection("", SEPARATOR).is_none());
+    }
+}
@@ -0,0 +1,176 @@
+// fetching a course's modules, pages, and assignments in a single graphql
+// query instead of a rest call (or several pages of them) each
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::entry::{self, Entry, EntryKind, Submission};
+use crate::parse_date;
+
+// how a course's content is fetched
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    #[default]
+    Rest,
+    Graphql,
+}
+
+pub const COURSE_QUERY: &str = r#"
+query Course($id: ID!, $pages: Boolean!, $assignments: Boolean!) {
+  course(id: $id) {
+    modulesConnection {
+      pageInfo { hasNextPage }
+      nodes {
+        moduleItems {
+          url
+          content {
+            __typename
+            ... on Assignment { _id title: name dueAt }
+            ... on Page { _id title pageUrl: url }
+            ... on Quiz { _id title }
+            ... on Discussion { _id title }
+            ... on File { _id title: displayName }
+            ... on ExternalUrl { title }
+            ... on ModuleExternalTool { title }
+          }
+        }
+      }
+    }
+    pagesConnection @include(if: $pages) {
+      pageInfo { hasNextPage }
+      nodes { _id title url updatedAt }
+    }
+    assignmentsConnection @include(if: $assignments) {
+      pageInfo { hasNextPage }
+      nodes {
+        _id name htmlUrl dueAt updatedAt assignmentGroupId
+        quiz { _id }
+        submissionsConnection(first: 1) {
+          nodes { workflow_state: state late missing }
+        }
+      }
+    }
+  }
+}
+"#;
+
+// what a course query gives back, split up the same way the rest endpoints
+// are so it can be merged the same way
+#[derive(Debug, Default)]
+pub struct CourseContent {
+    pub modules: Vec<Entry>,
+    pub pages: Vec<Entry>,
+    pub assignments: Vec<Entry>,
+}
+
+pub fn parse_course(res: &Value, course: &str) -> Result<CourseContent> {
+    if let Some(errors) = res["errors"].as_array().filter(|errors| !errors.is_empty()) {
+        anyhow::bail!(
+            "graphql error: {}",
+            errors[0]["message"].as_str().unwrap_or("unknown")
+        );
+    }
+    let data = &res["data"]["course"];
+    if data.is_null() {
+        anyhow::bail!("graphql didn't return the course");
+    }
+    // rather than following cursors, anything big enough to be paged is left
+    // to the rest endpoints
+    for connection in [
+        "modulesConnection",
+        "pagesConnection",
+        "assignmentsConnection",
+    ] {
+        if data[connection]["pageInfo"]["hasNextPage"].as_bool() == Some(true) {
+            anyhow::bail!("`{}` has more than one page", connection);
+        }
+    }
+
+    let mut content = CourseContent::default();
+
+    for module in nodes(&data["modulesConnection"]) {
+        for item in module["moduleItems"].as_array().into_iter().flatten() {
+            let item_content = &item["content"];
+            let (Some(title), Some(url)) = (item_content["title"].as_str(), item["url"].as_str())
+            else {
+                continue;
+            };
+            let kind = match item_content["__typename"].as_str().unwrap_or_default() {
+                "ModuleExternalTool" => EntryKind::ExternalTool,
+                type_name => EntryKind::from_module_item_type(type_name),
+            };
+            content.modules.push(Entry {
+                title: entry::single_line(title),
+                url: url.to_string(),
+                course: course.to_string(),
+                kind,
+                due_at: parse_date(&item_content["dueAt"]),
+                // module items link to themselves, so previews need the
+                // page's own url to find it
+                page_slug: item_content["pageUrl"]
+                    .as_str()
+                    .and_then(crate::preview::page_path)
+                    .map(|(_, slug)| slug.to_string()),
+                content_id: id(&item_content["_id"]),
+                ..Default::default()
+            });
+        }
+    }
+
+    for page in nodes(&data["pagesConnection"]) {
+        let (Some(title), Some(url)) = (page["title"].as_str(), page["url"].as_str()) else {
+            continue;
+        };
+        content.pages.push(Entry {
+            title: entry::single_line(title),
+            url: url.to_string(),
+            course: course.to_string(),
+            kind: EntryKind::Page,
+            recent_at: parse_date(&page["updatedAt"]),
+            page_slug: crate::preview::page_path(url).map(|(_, slug)| slug.to_string()),
+            content_id: id(&page["_id"]),
+            ..Default::default()
+        });
+    }
+
+    for assignment in nodes(&data["assignmentsConnection"]) {
+        let (Some(title), Some(url)) =
+            (assignment["name"].as_str(), assignment["htmlUrl"].as_str())
+        else {
+            continue;
+        };
+        // quizzes are linked from modules by their quiz id
+        let (kind, content_id) = match id(&assignment["quiz"]["_id"]) {
+            Some(quiz_id) => (EntryKind::Quiz, Some(quiz_id)),
+            None => (EntryKind::Assignment, id(&assignment["_id"])),
+        };
+        content.assignments.push(Entry {
+            title: entry::single_line(title),
+            url: url.to_string(),
+            course: course.to_string(),
+            kind,
+            due_at: parse_date(&assignment["dueAt"]),
+            recent_at: parse_date(&assignment["updatedAt"]),
+            content_id,
+            // only the user's own submission is visible to them, under the
+            // same field name rest uses
+            submission: Submission::from_json(&assignment["submissionsConnection"]["nodes"][0]),
+            assignment_group_id: id(&assignment["assignmentGroupId"]),
+            ..Default::default()
+        });
+    }
+
+    Ok(content)
+}
+
+fn nodes(connection: &Value) -> impl Iterator<Item = &Value> {
+    connection["nodes"].as_array().into_iter().flatten()
+}
+
+// graphql sends the database ids that rest uses as strings
+fn id(id: &Value) -> Option<u64> {
+    id.as_str()?.parse().ok()
+}
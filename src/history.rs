@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// when and how often each url has been opened from the finder
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct History {
+    visits: HashMap<String, Visit>,
+    // how many times each url was opened since loading, to add to whatever's
+    // been saved since by another run
+    #[serde(skip)]
+    opened: HashMap<String, u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Visit {
+    pub last_opened: DateTime<Utc>,
+    pub count: u32,
+}
+
+impl History {
+    // a missing or unreadable history file just means nothing has been opened
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        crate::paths::update_state_atomic(path, |current| {
+            let mut saved: History = current
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default();
+            for (url, count) in &self.opened {
+                let last_opened = self.visits[url].last_opened;
+                let visit = saved.visits.entry(url.clone()).or_insert(Visit {
+                    last_opened,
+                    count: 0,
+                });
+                visit.last_opened = visit.last_opened.max(last_opened);
+                visit.count += count;
+            }
+            Ok(serde_json::to_string(&saved)?)
+        })
+    }
+
+    pub fn record(&mut self, url: &str) {
+        let visit = self.visits.entry(url.to_string()).or_insert(Visit {
+            last_opened: Utc::now(),
+            count: 0,
+        });
+        visit.last_opened = Utc::now();
+        visit.count += 1;
+        *self.opened.entry(url.to_string()).or_default() += 1;
+    }
+
+    pub fn get(&self, url: &str) -> Option<&Visit> {
+        self.visits.get(url)
+    }
+}
@@ -0,0 +1,218 @@
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, LINK, RETRY_AFTER};
+use reqwest::{Certificate, Client, Proxy, Response};
+
+// how the client talks to canvas, from the `[network]` section of the config
+// file, environment variables, and flags
+#[derive(Debug, Default)]
+pub struct Network {
+    pub timeout: Option<Duration>,
+    pub proxy: Option<String>,
+    pub ca_bundle: Option<PathBuf>,
+    pub accept_invalid_certs: bool,
+    pub extra_headers: Vec<(String, String)>,
+}
+
+impl Network {
+    // catches combinations that can't work or would quietly do something
+    // unsafe
+    pub fn validate(&self) -> Result<()> {
+        if self.accept_invalid_certs && self.ca_bundle.is_some() {
+            anyhow::bail!(
+                "`ca_bundle` and `accept_invalid_certs` can't both be set, a ca bundle is only of use if certificates are checked"
+            );
+        }
+        if self.timeout == Some(Duration::ZERO) {
+            anyhow::bail!("the network timeout must be more than 0 seconds");
+        }
+        for (name, _) in &self.extra_headers {
+            if name.eq_ignore_ascii_case(AUTHORIZATION.as_str()) {
+                anyhow::bail!(
+                    "extra headers can't set `{}`, it's already used for the token",
+                    name
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub fn client(&self) -> Result<Client> {
+        let mut builder = Client::builder();
+
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder
+                .proxy(Proxy::all(proxy).with_context(|| format!("invalid proxy `{}`", proxy))?);
+        }
+        if let Some(ca_bundle) = &self.ca_bundle {
+            let pem = std::fs::read_to_string(ca_bundle)
+                .with_context(|| format!("couldn't read {}", ca_bundle.display()))?;
+            let certs = pem_blocks(&pem);
+            if certs.is_empty() {
+                anyhow::bail!("no certificates found in {}", ca_bundle.display());
+            }
+            for cert in certs {
+                builder = builder.add_root_certificate(
+                    Certificate::from_pem(cert.as_bytes()).with_context(|| {
+                        format!("invalid certificate in {}", ca_bundle.display())
+                    })?,
+                );
+            }
+        }
+        if self.accept_invalid_certs {
+            eprintln!("warning: certificates aren't being checked, anyone in the middle can read your token");
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.extra_headers {
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes())
+                    .with_context(|| format!("invalid header name `{}`", name))?,
+                HeaderValue::from_str(value)
+                    .with_context(|| format!("invalid value for header `{}`", name))?,
+            );
+        }
+
+        Ok(builder.default_headers(headers).build()?)
+    }
+}
+
+// reads a json body a chunk at a time, giving up as soon as it's bigger than
+// `max_bytes` rather than buffering whatever the server sends
+pub async fn read_json(res: Response, max_bytes: u64) -> Result<serde_json::Value> {
+    Ok(serde_json::from_slice(&read_body(res, max_bytes).await?)?)
+}
+
+// reads a whole body the same way, for `--record` to save
+pub async fn read_body(mut res: Response, max_bytes: u64) -> Result<Vec<u8>> {
+    let url = res.url().clone();
+    let too_big = || {
+        anyhow::anyhow!(
+            "response from {} is over the {}MB limit, raise `MAX_RESPONSE_MB` to allow it",
+            url,
+            max_bytes / (1024 * 1024)
+        )
+    };
+
+    if res.content_length().is_some_and(|len| len > max_bytes) {
+        return Err(too_big());
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = res.chunk().await? {
+        if body.len() as u64 + chunk.len() as u64 > max_bytes {
+            return Err(too_big());
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(body)
+}
+
+// the `rel="next"` url of a `Link` header like
+// `<https://a/?page=2>; rel="next", <https://a/?page=9>; rel="last"`
+pub fn next_link(headers: &HeaderMap) -> Option<String> {
+    for value in headers.get_all(LINK) {
+        let Ok(mut rest) = value.to_str() else {
+            continue;
+        };
+
+        while let Some(start) = rest.find('<') {
+            let end = start + rest[start..].find('>')?;
+            let url = &rest[start + 1..end];
+
+            // the parameters run up to the next link
+            let params_end = rest[end..].find('<').map_or(rest.len(), |i| end + i);
+            let is_next = rest[end + 1..params_end].split(';').any(|param| {
+                param
+                    .trim()
+                    .trim_end_matches(',')
+                    .strip_prefix("rel=")
+                    .is_some_and(|rel| {
+                        rel.trim_matches('"')
+                            .split_whitespace()
+                            .any(|rel| rel == "next")
+                    })
+            });
+            if is_next {
+                return Some(url.to_string());
+            }
+
+            rest = &rest[params_end..];
+        }
+    }
+    None
+}
+
+// how long a `Retry-After` header asks to wait, when it's given in seconds.
+// capped so a misbehaving server can't hang the finder
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let secs = headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(Duration::from_secs(secs.min(30)))
+}
+
+// splits a pem bundle into its certificates, since reqwest only reads one at a
+// time
+fn pem_blocks(pem: &str) -> Vec<&str> {
+    const END: &str = "-----END CERTIFICATE-----";
+    let mut blocks = Vec::new();
+    let mut rest = pem;
+    while let Some(start) = rest.find("-----BEGIN CERTIFICATE-----") {
+        let Some(len) = rest[start..].find(END) else {
+            break;
+        };
+        let end = start + len + END.len();
+        blocks.push(&rest[start..end]);
+        rest = &rest[end..];
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(LINK, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn follows_next_even_on_a_short_page() {
+        // canvas can send fewer than `per_page` items and still have more
+        let headers = link(
+            r#"<https://a/?page=1&per_page=100>; rel="current", <https://a/?page=2&per_page=100>; rel="next", <https://a/?page=9&per_page=100>; rel="last""#,
+        );
+        assert_eq!(
+            next_link(&headers).as_deref(),
+            Some("https://a/?page=2&per_page=100")
+        );
+    }
+
+    #[test]
+    fn stops_without_next() {
+        let headers =
+            link(r#"<https://a/?page=9>; rel="current", <https://a/?page=9>; rel="last""#);
+        assert_eq!(next_link(&headers), None);
+        assert_eq!(next_link(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn finds_next_among_several_rels() {
+        let headers = link(r#"<https://a/?page=2>; rel="next last""#);
+        assert_eq!(next_link(&headers).as_deref(), Some("https://a/?page=2"));
+    }
+}
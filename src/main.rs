@@ -1,16 +1,100 @@
 use core::panic;
-use std::fmt::Write;
-use std::fs::File;
-use std::path::PathBuf;
-use std::time::SystemTime;
+use std::collections::HashMap;
+use std::io::{IsTerminal, Read};
+use std::path::{Path, PathBuf};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use clap::Parser;
+use futures::StreamExt;
 use reqwest::*;
+use tokio::io::AsyncWriteExt;
+
+use cache::{EntriesCache, List};
+use cli::{Args, Command};
+use cli::{GroupBy, OnNoFinder, Sort};
+use config_file::ConfigFile;
+use downloads::Downloads;
+use entry::{DateFormat, Entry, EntryKind, RenderOptions, StatusIcons};
+use graphql::Backend;
+use history::History;
+use opener::Placement;
+use paths::Paths;
+use regex::Regex;
+
+mod cache;
+mod cli;
+mod color;
+mod config_file;
+mod downloads;
+mod entry;
+mod graphql;
+mod history;
+mod http;
+mod opener;
+mod paths;
+mod picker;
+mod preview;
+mod recording;
+#[cfg(unix)]
+mod stream;
+
+// how many courses are fetched at once
+const COURSE_CONCURRENCY: usize = 6;
+
+// how many courses `--time-budget` will fetch at once to finish in time
+const MAX_COURSE_CONCURRENCY: usize = 24;
+
+// how many previews `--prefetch-previews` fetches at once
+const PREFETCH_CONCURRENCY: usize = 4;
+
+// timeouts, rate limits, and server errors that usually go away on their own
+const DEFAULT_RETRY_STATUSES: [u16; 7] = [408, 425, 429, 500, 502, 503, 504];
 
 #[derive(Debug)]
 struct Course {
-    id: u32,
+    id: u64,
     name: String,
+    code: Option<String>,
+    nickname: Option<String>,
+    // the color picked for it on the dashboard
+    color: Option<(u8, u8, u8)>,
+    // whether it's starred in canvas, only looked up for `--favorites-first`
+    favorite: bool,
+}
+
+impl Course {
+    fn from_json(course: &serde_json::Value) -> Option<Self> {
+        // canvas swaps in the user's nickname for `name` and moves the real
+        // one to `original_name`
+        let (name, nickname) = match course["original_name"].as_str() {
+            Some(original) => (original, course["name"].as_str()),
+            None => (course["name"].as_str()?, None),
+        };
+
+        Some(Self {
+            id: course["id"].as_u64()?,
+            name: name.to_string(),
+            code: course["course_code"].as_str().map(str::to_string),
+            nickname: nickname.map(str::to_string),
+            color: course["course_color"].as_str().and_then(color::parse_hex),
+            favorite: false,
+        })
+    }
+
+    // whether `--course` picks this course, by id or by any of its names
+    fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        self.id.to_string() == query
+            || [Some(&self.name), self.code.as_ref(), self.nickname.as_ref()]
+                .into_iter()
+                .flatten()
+                .any(|name| name.to_lowercase() == query)
+    }
+
+    // what entries from this course are labelled with
+    fn label(&self) -> &str {
+        self.nickname.as_deref().unwrap_or(&self.name)
+    }
 }
 
 #[derive(Debug)]
@@ -19,14 +103,72 @@ pub struct Config {
     canvas_api_url: String,
     courses: Vec<Course>,
     os: OS,
-    current_dir: PathBuf,
+    paths: Paths,
+    date_format: DateFormat,
+    preview_cmd: String,
+    show_recency: bool,
+    include_pages: bool,
+    include_assignments: bool,
+    include_announcements: bool,
+    front_page_first: bool,
+    sort: Sort,
+    verify_before_open: bool,
+    pages_fallback: bool,
+    max_response_size: u64,
+    course_filter: Option<String>,
+    open_title: Option<String>,
+    prefetch_previews: usize,
+    retry_statuses: Vec<u16>,
+    max_retries: u32,
+    no_open: bool,
+    // never run another program, be it a terminal, finder, or browser
+    safe: bool,
+    network: http::Network,
+    hide_patterns: Vec<Regex>,
+    offline: bool,
+    download: bool,
+    finder_bin: String,
+    color: bool,
+    max_entries: Option<usize>,
+    status_icons: StatusIcons,
+    opener: Option<opener::Opener>,
+    only_new: bool,
+    backend: Backend,
+    ttls: cache::Ttls,
+    compact: bool,
+    include_grades_page: bool,
+    include_syllabus: bool,
+    truncate: Option<usize>,
+    favorites: bool,
+    group_by: Option<GroupBy>,
+    time_budget: Option<std::time::Duration>,
+    stats: bool,
+    multi: bool,
+    new_window: bool,
+    record: Option<PathBuf>,
+    replay: Option<PathBuf>,
+    announcements_digest: bool,
+    // added to every rest request on top of what's asked for
+    query_params: Vec<(String, String)>,
+    summary: bool,
+    favorites_first: bool,
+    kind_priority: HashMap<EntryKind, i32>,
+    // the query parameters taken off links before they're opened, with
+    // `--flatten-external`
+    strip_params: Vec<String>,
+    on_no_finder: OnNoFinder,
+    // what `--on-no-finder` switched to
+    print_entries: bool,
+    inline_picker: bool,
+    stream: bool,
 }
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 enum OS {
     Windows,
     MacOS,
-    Linux
+    Linux,
 }
 
 impl OS {
@@ -41,89 +183,586 @@ impl OS {
 }
 
 impl Config {
-    pub fn new() -> Result<Self> {
-        let current_dir = {
-            let mut t = dirs::home_dir().unwrap();
-            t.push("git");
-            t.push("canvas-fuzzy-finder");
-            t
+    // does whatever `--on-no-finder` says to once the finder can't be run
+    fn without_finder(&mut self, e: anyhow::Error) -> Result<()> {
+        match self.on_no_finder {
+            OnNoFinder::Error => anyhow::bail!(
+                "{:#}, or pass `--on-no-finder print` to print every entry or `--on-no-finder inline` to pick one here",
+                e
+            ),
+            OnNoFinder::Print => {
+                eprintln!("warning: {:#}, printing every entry instead", e);
+                self.print_entries = true;
+            }
+            OnNoFinder::Inline => {
+                eprintln!("warning: {:#}, picking from them here instead", e);
+                self.inline_picker = true;
+            }
+        }
+        self.stream = false;
+        Ok(())
+    }
+
+    // load environment variables, especially the `TOKEN`, preferring the
+    // config dir but still picking up a `.env` from the old `~/git` clone.
+    // one in the working directory isn't read, since a cloned repo's could
+    // point the finder or proxy somewhere else. returns where the `.env` is
+    // expected to be
+    fn load_env() -> Result<PathBuf> {
+        let env_file = Paths::config_dir()?.join(".env");
+        if env_file.exists() {
+            dotenv::from_path(&env_file)?;
+        } else if let Some(legacy) = Paths::legacy_dir().map(|dir| dir.join(".env")) {
+            if legacy.exists() {
+                dotenv::from_path(&legacy)?;
+            }
+        }
+        Ok(env_file)
+    }
+
+    pub fn new(args: &Args) -> Result<Self> {
+        let env_file = Self::load_env()?;
+
+        let paths = Paths::new()?;
+
+        let config_file = ConfigFile::load(&Paths::config_dir()?.join("config.toml"))?;
+
+        // a token piped in never ends up in a file or the process's arguments
+        if args.token_stdin && args.on_no_finder == OnNoFinder::Inline {
+            anyhow::bail!(
+                "`--token-stdin` can't be used with `--on-no-finder inline`, which picks from stdin"
+            );
+        }
+        let token = if args.token_stdin {
+            let mut token = String::new();
+            std::io::stdin()
+                .read_to_string(&mut token)
+                .context("couldn't read the token from stdin")?;
+            let token = token.trim();
+            if token.is_empty() {
+                anyhow::bail!("`--token-stdin` was given but nothing was piped in");
+            }
+            token.to_string()
+        } else {
+            std::env::var("TOKEN")
+                .with_context(|| format!("`TOKEN` is not set, add it to {}", env_file.display()))?
+        };
+
+        let canvas_url = std::env::var("CANVAS_API_URL").with_context(|| {
+            format!(
+                "`CANVAS_API_URL` is not set, add it to {}",
+                env_file.display()
+            )
+        })?;
+
+        // without `COURSE_IDS`, every active course is discovered from canvas
+        let courses = match std::env::var("COURSE_IDS") {
+            Ok(ids) => {
+                let names = std::env::var("COURSE_NAMES").unwrap_or_default();
+                let mut names = names.split(", ");
+                ids.split(", ")
+                    .map(|id| {
+                        let id = id.parse::<u64>().with_context(|| {
+                            format!("`{}` in `COURSE_IDS` isn't a course id", id)
+                        })?;
+                        Ok(Course {
+                            id,
+                            name: names
+                                .next()
+                                .map(str::to_string)
+                                .unwrap_or_else(|| id.to_string()),
+                            code: None,
+                            nickname: None,
+                            color: None,
+                            favorite: false,
+                        })
+                    })
+                    .collect::<Result<_>>()?
+            }
+            Err(_) => Vec::new(),
         };
 
-        std::env::set_current_dir(&current_dir)?;
+        let os = OS::new();
+        // powershell's script reads the whole buffer before starting fzf
+        if args.stream && matches!(os, OS::Windows) {
+            anyhow::bail!("`--stream` isn't supported on windows yet");
+        }
+
+        // how due dates are shown next to entries, `relative` or `absolute`
+        let date_format = match std::env::var("DATE_FORMAT") {
+            Ok(s) => DateFormat::parse(&s)?,
+            Err(_) => DateFormat::Relative,
+        };
 
-        // load environment variables, especially the `TOKEN`
-        dotenv::dotenv()?;
+        // the most a single response is allowed to be, in megabytes
+        let max_response_mb = match std::env::var("MAX_RESPONSE_MB") {
+            Ok(s) => s
+                .parse::<u64>()
+                .with_context(|| format!("`MAX_RESPONSE_MB` must be a number, got `{}`", s))?,
+            Err(_) => 50,
+        };
 
-        let token = std::env::var("TOKEN")?;
+        // statuses worth trying a request again for, and how many times to
+        let retry_statuses = match std::env::var("RETRY_STATUSES") {
+            Ok(s) => s
+                .split(',')
+                .map(|status| {
+                    status.trim().parse::<u16>().with_context(|| {
+                        format!("`{}` in `RETRY_STATUSES` isn't a status code", status)
+                    })
+                })
+                .collect::<Result<_>>()?,
+            Err(_) => DEFAULT_RETRY_STATUSES.to_vec(),
+        };
+        let max_retries = match std::env::var("MAX_RETRIES") {
+            Ok(s) => s
+                .parse::<u32>()
+                .with_context(|| format!("`MAX_RETRIES` must be a number, got `{}`", s))?,
+            Err(_) => 3,
+        };
 
-        let canvas_url = std::env::var("CANVAS_API_URL")?;
+        // flags win over the environment, which wins over the config file
+        let network_file = config_file.network;
+        let mut extra_headers: Vec<(String, String)> =
+            network_file.extra_headers.into_iter().collect();
+        for header in &args.headers {
+            let (name, value) = header
+                .split_once(':')
+                .with_context(|| format!("`--header {}` should look like `Name: value`", header))?;
+            let (name, value) = (name.trim().to_string(), value.trim().to_string());
+            extra_headers.retain(|(existing, _)| !existing.eq_ignore_ascii_case(&name));
+            extra_headers.push((name, value));
+        }
+        let query_params = args
+            .query_params
+            .iter()
+            .map(|param| {
+                let (key, value) = param.split_once('=').with_context(|| {
+                    format!("`--query-param {}` should look like `key=value`", param)
+                })?;
+                Ok((key.to_string(), value.to_string()))
+            })
+            .collect::<Result<_>>()?;
+        let network = http::Network {
+            timeout: layered(args.timeout, "TIMEOUT_SECS", network_file.timeout)?
+                .map(std::time::Duration::from_secs),
+            proxy: layered(args.proxy.clone(), "PROXY", network_file.proxy)?,
+            ca_bundle: layered(args.ca_bundle.clone(), "CA_BUNDLE", network_file.ca_bundle)?,
+            accept_invalid_certs: layered(
+                args.accept_invalid_certs.then_some(true),
+                "ACCEPT_INVALID_CERTS",
+                network_file.accept_invalid_certs,
+            )?
+            .unwrap_or(false),
+            extra_headers,
+        };
+        network.validate()?;
 
-        let courses = std::env::var("COURSE_IDS")
-            .unwrap()
-            .split(", ")
-            .zip(std::env::var("COURSE_NAMES").unwrap().split(", "))
-            .map(|(id, name)| Course {
-                id: id.parse::<u32>().unwrap(),
-                name: name.to_string(),
+        let hide_patterns = config_file
+            .hide_patterns
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern)
+                    .with_context(|| format!("invalid hide pattern `{}` in config.toml", pattern))
             })
-            .collect();
+            .collect::<Result<Vec<_>>>()?;
 
-        let os = OS::new();
+        // anything that reads lines on stdin and prints the picked one, like
+        // `sk`, can stand in for fzf
+        let finder_bin = layered(
+            args.finder_bin.clone(),
+            "FINDER_BIN",
+            config_file.finder_bin,
+        )?
+        .unwrap_or_else(|| "fzf".to_string());
+
+        // each icon can be swapped out on top of either built in set
+        let icons_file = config_file.status_icons;
+        let base_icons = if args.ascii_icons || icons_file.ascii {
+            StatusIcons::ascii()
+        } else {
+            StatusIcons::default()
+        };
+        let status_icons = StatusIcons {
+            submitted: icons_file.submitted.unwrap_or(base_icons.submitted),
+            missing: icons_file.missing.unwrap_or(base_icons.missing),
+            late: icons_file.late.unwrap_or(base_icons.late),
+            upcoming: icons_file.upcoming.unwrap_or(base_icons.upcoming),
+        };
+
+        // a per os section of `[browser]` wins over the rest of it
+        let browser_file = config_file.browser;
+        let browser_os = match os {
+            OS::Windows => browser_file.windows,
+            OS::MacOS => browser_file.macos,
+            OS::Linux => browser_file.linux,
+        }
+        .unwrap_or_default();
+        let opener = match args
+            .browser
+            .or(browser_os.name)
+            .or(browser_file.defaults.name)
+        {
+            Some(opener::Browser::Firefox)
+                if browser_os
+                    .remote_port
+                    .or(browser_file.defaults.remote_port)
+                    .is_some() =>
+            {
+                anyhow::bail!("`remote_port` only works with chromium-based browsers, not firefox")
+            }
+            Some(browser) => Some(opener::Opener {
+                browser,
+                command: browser_os.command.or(browser_file.defaults.command),
+                remote_port: browser_os.remote_port.or(browser_file.defaults.remote_port),
+                profile: args
+                    .profile
+                    .clone()
+                    .or(browser_os.profile)
+                    .or(browser_file.defaults.profile),
+            }),
+            None if args.profile.is_some() => {
+                anyhow::bail!("`--profile` needs a `--browser` to know how to pass it on")
+            }
+            None if args.new_window => {
+                anyhow::bail!("`--new-window` needs a `--browser` to know how to ask for one")
+            }
+            None => None,
+        };
+
+        let ttl_file = config_file.cache_ttl;
+        let ttl = |ttl: Option<String>, default| {
+            ttl.map_or(Ok(default), |ttl| cache::parse_ttl(&ttl))
+                .context("invalid `[cache_ttl]` in config.toml")
+        };
+        let defaults = cache::Ttls::default();
+        let ttls = cache::Ttls {
+            modules: ttl(ttl_file.modules, defaults.modules)?,
+            pages: ttl(ttl_file.pages, defaults.pages)?,
+            assignments: ttl(ttl_file.assignments, defaults.assignments)?,
+            announcements: ttl(ttl_file.announcements, defaults.announcements)?,
+        };
+
+        // fzf fills in `{}` with the highlighted line
+        let preview_cmd = match &args.preview_cmd {
+            Some(cmd) if cmd.trim().is_empty() => {
+                anyhow::bail!("`--preview-cmd` must not be empty")
+            }
+            Some(cmd) => cmd.clone(),
+            None => format!(
+                "{} {}preview {{}}",
+                shell_quote(&std::env::current_exe()?.to_string_lossy()),
+                preview_flags(args)
+                    .iter()
+                    .map(|flag| shell_quote(flag) + " ")
+                    .collect::<String>()
+            ),
+        };
 
         Ok(Self {
             token,
             canvas_api_url: canvas_url,
             courses,
-            current_dir,
+            paths,
             os,
+            date_format,
+            preview_cmd,
+            show_recency: args.show_recency,
+            include_pages: args.include_pages,
+            include_assignments: args.include_assignments,
+            include_announcements: args.include_announcements || args.announcements_digest,
+            front_page_first: args.front_page_first,
+            sort: args.sort,
+            verify_before_open: args.verify_before_open,
+            pages_fallback: !args.no_pages_fallback,
+            max_response_size: max_response_mb * 1024 * 1024,
+            course_filter: args.course.clone(),
+            open_title: args.open_title.clone(),
+            prefetch_previews: args.prefetch_previews,
+            retry_statuses,
+            max_retries,
+            no_open: args.no_open || args.safe,
+            safe: args.safe,
+            network,
+            hide_patterns,
+            offline: args.offline,
+            download: args.download,
+            finder_bin,
+            max_entries: args.max_entries,
+            status_icons,
+            opener,
+            only_new: args.only_new,
+            ttls,
+            compact: args.compact,
+            include_grades_page: args.include_grades_page,
+            include_syllabus: args.include_syllabus,
+            truncate: args.truncate,
+            favorites: args.favorites,
+            group_by: args.group_by,
+            time_budget: args.time_budget.map(std::time::Duration::from_secs),
+            stats: args.stats,
+            multi: args.multi,
+            new_window: args.new_window,
+            record: args.record.clone(),
+            replay: args.replay.clone(),
+            announcements_digest: args.announcements_digest,
+            query_params,
+            summary: args.summary,
+            favorites_first: args.favorites_first,
+            kind_priority: config_file.kind_priority,
+            strip_params: match args.flatten_external {
+                true => config_file
+                    .strip_params
+                    .unwrap_or_else(|| opener::DEFAULT_STRIP_PARAMS.map(str::to_string).to_vec()),
+                false => Vec::new(),
+            },
+            on_no_finder: args.on_no_finder,
+            print_entries: false,
+            inline_picker: false,
+            stream: args.stream,
+            backend: args.backend.or(config_file.backend).unwrap_or_default(),
+            // https://no-color.org
+            color: !args.safe && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()),
         })
     }
 }
 
+// where each course's entries are sent as soon as they're fetched, for
+// `--stream`
+type Progress = tokio::sync::mpsc::UnboundedSender<Vec<Entry>>;
+
 #[derive(Debug)]
 struct Runner {
     config: Config,
     client: Client,
+    // `None` when the token isn't allowed to look up its own user, in which
+    // case anything user-scoped (planner, todo, conversations) is skipped
+    #[allow(dead_code)]
     user_id: Option<u64>,
     recache_all: bool,
+    // `--record` or `--replay`
+    session: Option<recording::Session>,
 }
 
 impl Runner {
-    pub fn new(config: Config) -> Self {
-        let client = Client::new();
-        Self {
+    pub fn new(config: Config) -> Result<Self> {
+        let client = config.network.client()?;
+        let session = match (&config.record, &config.replay) {
+            (Some(dir), _) => Some(recording::Session::record(
+                dir,
+                &config.token,
+                config.max_response_size,
+            )?),
+            (_, Some(dir)) => Some(recording::Session::replay(dir)?),
+            _ => None,
+        };
+        Ok(Self {
+            // everything has to be asked for to be recorded or replayed,
+            // rather than come from the cache
+            recache_all: session.is_some(),
+            session,
             config,
             client,
             user_id: None,
-            recache_all: false,
+        })
+    }
+
+    // tokens scoped to courses get a 403 here, which shouldn't stop them from
+    // searching course content
+    async fn look_up_user(&mut self) {
+        if let Err(e) = self.set_user_id().await {
+            eprintln!(
+                "warning: couldn't look up your canvas user ({:#}), skipping user-scoped features",
+                e
+            );
         }
     }
 
     async fn set_user_id(&mut self) -> Result<()> {
-        let res = self
-            .client
-            .get(format!("{}/api/v1/users/self", &self.config.canvas_api_url))
-            .bearer_auth(&self.config.token)
-            .send()
-            .await?
-            .json::<serde_json::Value>()
-            .await?;
-        dbg!(&res);
+        let res = self.get_json("users/self", &[]).await?;
 
-        self.user_id = Some(res["id"].as_u64().unwrap());
+        self.user_id = Some(
+            res["id"]
+                .as_u64()
+                .ok_or_else(|| anyhow::anyhow!("unexpected user response: {}", res))?,
+        );
 
         Ok(())
     }
 
-    fn fuzzy_find(&self, str: &str) -> String {
+    async fn fuzzy_find(&self, str: &str, header: Option<&str>) -> Result<String> {
+        if self.config.safe || self.config.inline_picker {
+            return picker::pick(str, header);
+        }
+
+        let (finder, fzf_args) = self.finder(header)?;
         match self.config.os {
-            OS::Windows => windows::fuzzy_finder(&self.config, str),
-            OS::MacOS => macos::fuzzy_finder(&self.config, str),
-            OS::Linux => linux::fuzzy_finder(&self.config, str),
+            OS::Windows => windows::fuzzy_finder(&self.config, &finder, str, &fzf_args).await,
+            OS::MacOS => macos::fuzzy_finder(&self.config, &finder, str, &fzf_args).await,
+            OS::Linux => linux::fuzzy_finder(&self.config, &finder, str, &fzf_args).await,
+        }
+    }
+
+    // opens the finder on `seed` while the entries are fetched again, adding
+    // each course's to it as they come in. anything already in `entries` is
+    // left out, and whatever's added is kept in `streamed` to look the
+    // selection up in. what's added goes at the end, however it'd otherwise
+    // be sorted
+    #[cfg(unix)]
+    async fn stream_find(
+        &self,
+        seed: &str,
+        header: Option<&str>,
+        entries: &[Entry],
+        options: &RenderOptions<'_>,
+        streamed: &std::cell::RefCell<Vec<Entry>>,
+    ) -> Result<String> {
+        let (finder, fzf_args) = self.finder(header)?;
+
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let fetch = async move {
+            // the final list is sent too, for anything that didn't come
+            // from a course fetched this time (e.g. one served from the
+            // cache)
+            match self.get_modules(Some(&tx)).await {
+                Ok((entries, _)) => {
+                    let _ = tx.send(entries);
+                }
+                Err(e) => eprintln!("warning: couldn't refresh the entries ({:#})", e),
+            }
+        };
+
+        let mut seen: std::collections::HashSet<String> =
+            entries.iter().map(|entry| entry.url.clone()).collect();
+        let lines = futures::stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|batch| (batch, rx))
+        })
+        .filter_map(move |batch| {
+            let new: Vec<Entry> = batch
+                .into_iter()
+                .filter(|entry| !self.is_hidden(entry) && seen.insert(entry.url.clone()))
+                .collect();
+            let line = match new.is_empty() {
+                true => None,
+                false => entry::render(&new, options).ok(),
+            };
+            streamed.borrow_mut().extend(new);
+            std::future::ready(line)
+        });
+
+        let finder = stream::fuzzy_finder(&self.config, &finder, seed, &fzf_args, lines);
+        tokio::pin!(finder);
+        let prefetch = self.prefetch_previews(entries, self.config.prefetch_previews);
+        tokio::select! {
+            picked = &mut finder => picked,
+            _ = futures::future::join(fetch, prefetch) => finder.await,
+        }
+    }
+
+    #[cfg(not(unix))]
+    async fn stream_find(
+        &self,
+        _seed: &str,
+        _header: Option<&str>,
+        _entries: &[Entry],
+        _options: &RenderOptions<'_>,
+        _streamed: &std::cell::RefCell<Vec<Entry>>,
+    ) -> Result<String> {
+        anyhow::bail!("`--stream` isn't supported here")
+    }
+
+    // the finder to run and what to run it with. checked here rather than
+    // left to the terminal, which would just close without saying why
+    fn finder(&self, header: Option<&str>) -> Result<(PathBuf, Vec<String>)> {
+        let finder = find_executable(&self.config.finder_bin).with_context(|| {
+            format!(
+                "couldn't find the finder `{}`, install fzf or point `--finder-bin` at it",
+                self.config.finder_bin
+            )
+        })?;
+        let mut fzf_args = self.fzf_args();
+        if let Some(header) = header {
+            fzf_args.extend(["--header".to_string(), header.to_string()]);
+        }
+        Ok((finder, fzf_args))
+    }
+
+    // hidden entries are still cached, so changing the patterns doesn't need
+    // a refetch
+    fn is_hidden(&self, entry: &Entry) -> bool {
+        self.config
+            .hide_patterns
+            .iter()
+            .any(|pattern| pattern.is_match(&entry.title))
+    }
+
+    // extra arguments handed through the scripts to fzf
+    fn fzf_args(&self) -> Vec<String> {
+        let mut args = vec!["--preview".to_string(), self.config.preview_cmd.clone()];
+        if self.config.color {
+            args.push("--ansi".to_string());
         }
+        // the url and course are still on the line, off screen. scrolling
+        // over to show where they matched would bring them back into view
+        if self.config.compact {
+            args.push("--no-hscroll".to_string());
+        }
+        if self.config.multi {
+            args.push("--multi".to_string());
+        }
+        args
+    }
+
+    async fn open_link(&self, url: &str, placement: Placement) -> Result<()> {
+        // `--safe` turns on `--no-open`, so nothing should get here
+        if self.config.safe {
+            anyhow::bail!("`--safe` doesn't run another program to open {}", url);
+        }
+        let url = &match self.config.strip_params.is_empty() {
+            true => url.to_string(),
+            false => opener::strip_params(url, &self.config.strip_params),
+        };
+        if let Some(opener) = &self.config.opener {
+            // the protocol only opens tabs, so a new window is asked for by
+            // starting the browser as usual
+            if let Some(port) = opener.remote_port.filter(|_| placement == Placement::Tab) {
+                match opener.open_remote(port, url).await {
+                    Ok(()) => return Ok(()),
+                    // it just isn't running yet
+                    Err(e)
+                        if e.downcast_ref::<reqwest::Error>()
+                            .is_some_and(|e| e.is_connect()) => {}
+                    Err(e) => eprintln!(
+                        "warning: couldn't reach {:?} on port {} ({:#}), starting it",
+                        opener.browser, port, e
+                    ),
+                }
+            }
+            // a browser that wasn't already running stays in the foreground
+            // until it's quit, so it's left to run on its own. its output
+            // would only land in the terminal
+            let started = opener
+                .command(&self.config.os, url, placement)
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .spawn();
+            if let Err(e) = started {
+                eprintln!(
+                    "warning: couldn't run {:?} ({}), opening {} as usual",
+                    opener.browser, e, url
+                );
+            } else {
+                return Ok(());
+            }
+        }
+        self.open_local(url)
     }
 
-    fn open_link(&self, url: &str) {
+    // opens a link or file with whatever the system opens it with
+    fn open_local(&self, url: &str) -> Result<()> {
+        if self.config.safe {
+            anyhow::bail!("`--safe` doesn't run another program to open {}", url);
+        }
         match self.config.os {
             OS::Windows => {
                 windows::open_link(url);
@@ -135,204 +774,1767 @@ impl Runner {
                 linux::open_link(url);
             }
         }
+        Ok(())
     }
 
-    // gets a list of all the titles, urls, and course names of all pages from
-    // all modules for a user
-    async fn get_modules(&self) -> Result<String> {
-        let mut buf = String::new();
+    // fills in the course list from canvas if none were configured, then
+    // narrows it down to `--course`
+    async fn resolve_courses(&mut self) -> Result<()> {
+        if self.config.courses.is_empty() {
+            self.config.courses = self.get_active_courses().await?;
+            if self.config.courses.is_empty() {
+                anyhow::bail!("no active courses found, set `COURSE_IDS` to pick some");
+            }
 
-        if !self.recache_all {
-            // check if buf file exists
-            if let Ok(file) = File::open({
-                let mut t = self.config.current_dir.clone();
-                t.push("buf");
-                t
-            }) {
-                let sys_time_now = SystemTime::now();
-                let duration = sys_time_now.duration_since(file.metadata()?.modified()?)?;
-                if duration < std::time::Duration::new(300, 0) {
-                    return Ok(std::fs::read_to_string({
-                        let mut t = self.config.current_dir.clone();
-                        t.push("buf");
-                        t
-                    })?);
+            // the courses starred in canvas, as long as they're still active
+            if self.config.favorites {
+                let favorites = self.get_favorite_ids().await?;
+                self.config
+                    .courses
+                    .retain(|course| favorites.contains(&course.id));
+                if self.config.courses.is_empty() {
+                    anyhow::bail!("none of your active courses are favorited in canvas");
                 }
             }
         }
 
-        // get all course ids/names in .env
-        for course in &self.config.courses {
-            // get module page of every course
-            let modules = self
-                .client
-                .get(format!(
-                    "{}/api/v1/courses/{}/modules",
-                    &self.config.canvas_api_url, course.id
-                ))
-                .bearer_auth(&self.config.token)
-                .query(&[("include[]", "items"), ("per_page", "100")])
-                .send()
-                .await?
-                .json::<serde_json::Value>()
-                .await?;
-
-            // for every item (or dropdown menu in modules)
-            for module in modules.as_array().unwrap() {
-                for page in module["items"].as_array().unwrap() {
-                    if page["title"].as_str().is_none() || page["html_url"].as_str().is_none() {
-                        continue;
+        if self.config.favorites_first && !self.config.favorites {
+            match self.get_favorite_ids().await {
+                Ok(favorites) => {
+                    for course in &mut self.config.courses {
+                        course.favorite = favorites.contains(&course.id);
                     }
-
-                    writeln!(
-                        &mut buf,
-                        "{} || {} || {}",
-                        page["title"].as_str().unwrap(),
-                        page["html_url"].as_str().unwrap(),
-                        course.name
-                    )?;
                 }
+                Err(e) => eprintln!("warning: couldn't look up favorite courses: {:#}", e),
+            }
+        }
+
+        if let Some(query) = &self.config.course_filter {
+            self.config.courses.retain(|course| course.matches(query));
+            if self.config.courses.is_empty() {
+                anyhow::bail!(
+                    "no course matches `{}`, see `list-courses` for what's available",
+                    query
+                );
             }
         }
-        Ok(buf)
+        Ok(())
     }
-}
 
-mod windows {
-    use std::process::Command;
+    // the ids of the courses starred in canvas
+    async fn get_favorite_ids(&self) -> Result<Vec<u64>> {
+        Ok(self
+            .get_paginated("users/self/favorites/courses", &[("per_page", "100")])
+            .await?
+            .iter()
+            .filter_map(|course| course["id"].as_u64())
+            .collect())
+    }
 
-    use crate::Config;
+    // every course the user is actively enrolled in
+    async fn get_active_courses(&self) -> Result<Vec<Course>> {
+        let courses = self
+            .get_paginated(
+                "courses",
+                &[("enrollment_state", "active"), ("per_page", "100")],
+            )
+            .await?;
 
-    pub fn fuzzy_finder(config: &Config, str: &str) -> String {
-        // write buffer to current directory
-        std::fs::write(
-            {
-                let mut t = config.current_dir.clone();
-                t.push("buf");
-                t
-            },
-            str,
-        )
-        .unwrap();
-
-        // Open kitty with fzf in the external files directory
-        Command::new("pwsh")
-            .args([
-                "-File",
-                {
-                    let mut t = config.current_dir.clone();
-                    t.push("fzf-to-title-url-name.ps1");
-                    t
+        // courses that haven't started yet only come back with an id
+        Ok(courses.iter().filter_map(Course::from_json).collect())
+    }
+
+    // prints every course with its id, name, code, and nickname, one per line
+    // separated by tabs
+    async fn list_courses(&mut self) -> Result<()> {
+        if self.config.courses.is_empty() {
+            self.resolve_courses().await?;
+        } else {
+            // configured courses only know their id and name, so fill in the
+            // rest from canvas where possible
+            match self.get_active_courses().await {
+                Ok(active) => {
+                    for course in &mut self.config.courses {
+                        if let Some(found) = active.iter().find(|c| c.id == course.id) {
+                            course.code = found.code.clone();
+                            course.nickname = found.nickname.clone();
+                        }
+                    }
                 }
-                .to_str()
-                .unwrap(),
-            ])
-            .output()
-            .unwrap();
-
-        std::fs::read_to_string({
-            let mut t = config.current_dir.clone();
-            t.push("title-url-name.txt");
-            t
-        })
-        .unwrap()
-        .trim()
-        .to_string()
+                Err(e) => eprintln!("warning: couldn't look up course details: {:#}", e),
+            }
+        }
+
+        for course in &self.config.courses {
+            println!(
+                "{}\t{}\t{}\t{}",
+                course.id,
+                course.name,
+                course.code.as_deref().unwrap_or("-"),
+                course.nickname.as_deref().unwrap_or("-")
+            );
+        }
+        Ok(())
     }
-    pub fn open_link(url: &str) {
-        Command::new("explorer").arg(url).output().unwrap();
+
+    // sends an authenticated request, retrying with backoff on any of the
+    // `RETRY_STATUSES`
+    async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let request = request.bearer_auth(&self.config.token);
+        let mut attempt = 0;
+
+        loop {
+            let Some(retry) = request
+                .try_clone()
+                .filter(|_| attempt < self.config.max_retries)
+            else {
+                return Ok(self.execute(request).await?.error_for_status()?);
+            };
+
+            let res = self.execute(retry).await?;
+            if !self.config.retry_statuses.contains(&res.status().as_u16()) {
+                return Ok(res.error_for_status()?);
+            }
+
+            // rate limits say how long to back off for
+            let delay = http::retry_after(res.headers())
+                .unwrap_or_else(|| std::time::Duration::from_millis(500 * 2u64.pow(attempt)));
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
     }
-}
 
-mod macos {
-    use std::process::Command;
+    // sends a request as is, through the recording if there is one
+    async fn execute(&self, request: RequestBuilder) -> Result<Response> {
+        match &self.session {
+            Some(session) => session.execute(&self.client, request.build()?).await,
+            None => Ok(request.send().await?),
+        }
+    }
 
-    use crate::Config;
+    // saves a course file into the downloads dir, returning where it went
+    async fn download_file(&self, file_id: u64) -> Result<PathBuf> {
+        let file = self.get_json(&format!("files/{}", file_id), &[]).await?;
+        let (Some(download_url), Some(name)) =
+            (file["url"].as_str(), file["display_name"].as_str())
+        else {
+            anyhow::bail!("canvas didn't give a download link for file {}", file_id);
+        };
+
+        // names come from whoever uploaded the file, so only the last path
+        // component is kept
+        let name = Path::new(name)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| file_id.to_string());
+        let local = self.config.paths.download(file_id, &name);
+
+        let mut res = self.send(self.client.get(download_url)).await?;
+        let mut out = tokio::fs::File::create(&local)
+            .await
+            .with_context(|| format!("couldn't create {}", local.display()))?;
+        while let Some(chunk) = res.chunk().await? {
+            out.write_all(&chunk).await?;
+        }
+        out.flush().await?;
+
+        Ok(local)
+    }
+
+    // gets `/api/v1/{path}` as json
+    async fn get_json(&self, path: &str, query: &[(&str, &str)]) -> Result<serde_json::Value> {
+        let res = self
+            .send(
+                self.client
+                    .get(format!("{}/api/v1/{}", &self.config.canvas_api_url, path))
+                    .query(query)
+                    .query(&self.config.query_params),
+            )
+            .await?;
+
+        http::read_json(res, self.config.max_response_size).await
+    }
+
+    // gets every page of a list endpoint, following the `Link` header's
+    // `rel="next"` until there isn't one. canvas is free to return fewer than
+    // `per_page` items on a page that isn't the last, so a short page says
+    // nothing about whether there's more
+    async fn get_paginated(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<Vec<serde_json::Value>> {
+        let first = self
+            .client
+            .get(format!("{}/api/v1/{}", &self.config.canvas_api_url, path))
+            .query(query)
+            .query(&self.config.query_params)
+            .build()?
+            .url()
+            .to_string();
+
+        // a listing too big to get through in one go carries on from
+        // wherever the last run stopped
+        let cursor_path = self.config.paths.cursor(&first);
+        let cursor = (!self.recache_all && cache::is_fresh(&cursor_path, cache::CURSOR_TTL))
+            .then(|| cache::read_text(&cursor_path).ok())
+            .flatten()
+            .and_then(|cursor| serde_json::from_str::<cache::Cursor>(&cursor).ok());
+        let (mut items, mut url) = match cursor {
+            Some(cursor) => (cursor.items, cursor.next),
+            None => (Vec::new(), first),
+        };
 
-    pub fn fuzzy_finder(config: &Config, str: &str) -> String {
-        // write buffer to current directory
-        std::fs::write("buf", str).unwrap();
-
-        // Open kitty with fzf in the external files directory
-        Command::new("kitty")
-            .args([
-                "sh",
-                {
-                    let mut t = config.current_dir.clone();
-                    t.push("fzf-to-title-url-name.sh");
-                    t
+        // saved after every page rather than only on an error, since the
+        // run can also be stopped partway, by ctrl-c or by a pick with
+        // `--stream`. a cursor that can't be saved is only warned about once
+        let mut saving = true;
+        loop {
+            let (page, next) = self.get_page(path, &url).await?;
+            items.extend(page);
+            let Some(next) = next else {
+                break;
+            };
+            url = next;
+
+            if saving {
+                let cursor = cache::Cursor {
+                    next: url.clone(),
+                    items: items.clone(),
+                };
+                if let Err(e) = std::fs::write(&cursor_path, serde_json::to_string(&cursor)?) {
+                    eprintln!("warning: couldn't save where `{}` stopped: {}", path, e);
+                    saving = false;
                 }
-                .to_str()
-                .unwrap(),
-            ])
-            .output()
-            .unwrap();
-
-        std::fs::read_to_string({
-            let mut t = config.current_dir.clone();
-            t.push("title-url-name.txt");
-            t
-        })
-        .unwrap()
-        .trim()
-        .to_string()
+            }
+        }
+
+        // all of it's been fetched, so there's nothing to resume
+        if let Err(e) = std::fs::remove_file(&cursor_path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                eprintln!("warning: couldn't remove {}: {}", cursor_path.display(), e);
+            }
+        }
+        Ok(items)
     }
-    pub fn open_link(url: &str) {
-        Command::new("open").arg(url).output().unwrap();
+
+    // one page of a list endpoint, along with the url of the next one. the
+    // next url already carries the query
+    async fn get_page(
+        &self,
+        path: &str,
+        url: &str,
+    ) -> Result<(Vec<serde_json::Value>, Option<String>)> {
+        let res = self.send(self.client.get(url)).await?;
+        let next = http::next_link(res.headers());
+        match http::read_json(res, self.config.max_response_size).await? {
+            serde_json::Value::Array(page) => Ok((page, next)),
+            other => anyhow::bail!("unexpected response from `{}`: {}", path, other),
+        }
     }
-}
 
-mod linux {
-    use std::process::Command;
+    // whether a url 404s. anything else, including not being able to reach it
+    // at all, is left for the browser to deal with
+    async fn is_dead_link(&self, url: &str) -> bool {
+        match self
+            .execute(self.client.head(url).bearer_auth(&self.config.token))
+            .await
+        {
+            Ok(res) => matches!(res.status(), StatusCode::NOT_FOUND | StatusCode::GONE),
+            Err(_) => false,
+        }
+    }
 
-    use crate::Config;
+    // describes everything that affects what gets fetched
+    fn cache_key(&self) -> String {
+        let course_ids: Vec<String> = self
+            .config
+            .courses
+            .iter()
+            .map(|course| course.id.to_string())
+            .collect();
+        format!(
+            "{} courses={} pages={} pages_fallback={} assignments={} announcements={} grades={} syllabus={} groups={} digest={} params={:?}",
+            self.config.canvas_api_url,
+            course_ids.join(","),
+            self.config.include_pages,
+            self.config.pages_fallback,
+            self.config.include_assignments,
+            self.config.include_announcements,
+            self.config.include_grades_page,
+            self.config.include_syllabus,
+            self.config.group_by == Some(GroupBy::AssignmentGroup),
+            self.config.announcements_digest,
+            self.config.query_params
+        )
+    }
+
+    // gets a list of all the titles, urls, and course names of all pages from
+    // all modules for a user, along with how old they are
+    async fn get_modules(&self, progress: Option<&Progress>) -> Result<(Vec<Entry>, cache::Age)> {
+        let cache_path = self.config.paths.entries_cache();
+        let key = self.cache_key();
+
+        // whatever was fetched last is all there is to go on
+        if self.config.offline {
+            let cache = cache::read_text(&cache_path).with_context(|| {
+                format!(
+                    "nothing has been fetched into {} yet, run once without `--offline`",
+                    cache_path.display()
+                )
+            })?;
+            let cache = serde_json::from_str::<EntriesCache>(&cache)?;
+            let age = cache::Age::of(&cache);
+            return Ok((cache.entries, age));
+        }
 
-    pub fn fuzzy_finder(config: &Config, str: &str) -> String {
-        // write buffer to current directory
-        std::fs::write("buf", str).unwrap();
-
-        // Open kitty with fzf in the external files directory
-        Command::new("kitty")
-            .args([
-                "sh",
-                {
-                    let mut t = config.current_dir.clone();
-                    t.push("fzf-to-title-url-name.sh");
-                    t
+        if !self.recache_all {
+            // check if cache file exists
+            if cache::is_fresh(&cache_path, self.config.ttls.shortest()) {
+                if let Ok(cache) = cache::read_text(&cache_path) {
+                    if let Ok(cache) = serde_json::from_str::<EntriesCache>(&cache) {
+                        if cache.key == key {
+                            let age = cache::Age::of(&cache);
+                            return Ok((cache.entries, age));
+                        }
+                    }
                 }
-                .to_str()
-                .unwrap(),
-            ])
-            .output()
-            .unwrap();
-
-        std::fs::read_to_string({
-            let mut t = config.current_dir.clone();
-            t.push("title-url-name.txt");
-            t
-        })
-        .unwrap()
-        .trim()
-        .to_string()
+            }
+        }
+
+        let mut entries = Vec::new();
+
+        // fetch a few courses at once, keeping going if only some of them
+        // fail
+        let started = std::time::Instant::now();
+        let (results, concurrency) = self.fetch_courses(progress).await;
+        if self.config.stats {
+            eprintln!(
+                "fetched {} courses in {:.1}s, {} at a time",
+                self.config.courses.len(),
+                started.elapsed().as_secs_f64(),
+                concurrency
+            );
+        }
+
+        let mut failures = Vec::new();
+        for (course, result) in self.config.courses.iter().zip(results) {
+            match result {
+                Ok(course_entries) => {
+                    entries.extend(course_entries);
+                    // canvas always has a grades page, so it's not fetched
+                    if self.config.include_grades_page {
+                        entries.push(Entry {
+                            title: "Grades".to_string(),
+                            url: format!(
+                                "{}/courses/{}/grades",
+                                self.config.canvas_api_url, course.id
+                            ),
+                            course: course.label().to_string(),
+                            ..Default::default()
+                        });
+                    }
+                }
+                Err(e) => failures.push((course, e)),
+            }
+        }
+
+        if !failures.is_empty() {
+            eprintln!(
+                "failed to fetch {} of {} courses:",
+                failures.len(),
+                self.config.courses.len()
+            );
+            for (course, e) in &failures {
+                eprintln!("  {} ({}): {:#}", course.name, course.id, e);
+            }
+            if failures.len() == self.config.courses.len() {
+                anyhow::bail!("failed to fetch any courses");
+            }
+        }
+
+        // a partial list isn't cached, or the failed courses would be
+        // missing until it expired. the courses that were fetched are still
+        // in their own caches, so trying again next run is cheap
+        if !failures.is_empty() {
+            return Ok((entries, cache::Age::Fresh));
+        }
+
+        // kept for `--only-new` to compare against
+        if cache_path.exists() {
+            std::fs::rename(&cache_path, self.config.paths.previous_entries())?;
+        }
+        let cache = EntriesCache {
+            key,
+            entries,
+            fetched_at: Some(chrono::Utc::now()),
+        };
+        std::fs::write(&cache_path, serde_json::to_string(&cache)?)?;
+
+        Ok((cache.entries, cache::Age::Fresh))
     }
-    pub fn open_link(url: &str) {
-        Command::new("xdg-open").arg(url).output().unwrap();
+
+    // fetches every course, returned in order, along with the most courses that
+    // were fetched at once. favorites are started first, so they're ready
+    // soonest. with a time budget, whenever a course finishes and the
+    // rest look like they'd take too long at the current pace, more are
+    // fetched at once (up to a cap) to catch up
+    async fn fetch_courses(&self, progress: Option<&Progress>) -> (Vec<Result<Vec<Entry>>>, usize) {
+        let courses = &self.config.courses;
+        let started = std::time::Instant::now();
+        let mut concurrency = COURSE_CONCURRENCY;
+        let mut most = 0;
+
+        let mut order: Vec<_> = courses.iter().enumerate().collect();
+        order.sort_by_key(|(_, course)| !course.favorite);
+        let mut pending = order.into_iter();
+        let mut running = futures::stream::FuturesUnordered::new();
+        let mut results: Vec<_> = courses.iter().map(|_| None).collect();
+        let mut done = 0;
+        loop {
+            while running.len() < concurrency {
+                let Some((i, course)) = pending.next() else {
+                    break;
+                };
+                running.push(async move { (i, self.get_course(course).await) });
+            }
+            most = most.max(running.len());
+
+            let Some((i, result)) = running.next().await else {
+                break;
+            };
+            if let (Some(progress), Ok(entries)) = (progress, &result) {
+                let _ = progress.send(entries.clone());
+            }
+            results[i] = Some(result);
+            done += 1;
+
+            if let Some(budget) = self.config.time_budget {
+                // when the rest would be done if courses keep finishing as
+                // quickly as they have so far
+                let elapsed = started.elapsed();
+                let left = (courses.len() - done) as u32;
+                let projected = elapsed + elapsed * left / done as u32;
+                if left > 0 && projected > budget && concurrency < MAX_COURSE_CONCURRENCY {
+                    concurrency = (concurrency * 2).min(MAX_COURSE_CONCURRENCY);
+                }
+            }
+        }
+
+        let results = results
+            .into_iter()
+            .map(|result| result.expect("every course is fetched"))
+            .collect();
+        (results, most)
     }
-}
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let config = Config::new()?;
+    // everything listed for a single course
+    async fn get_course(&self, course: &Course) -> Result<Vec<Entry>> {
+        let mut entries = None;
+        if self.config.backend == Backend::Graphql {
+            match self.fetch_course_graphql(course).await {
+                Ok(fetched) => entries = Some(fetched),
+                Err(e) => eprintln!(
+                    "couldn't fetch {} over graphql ({:#}), falling back to rest",
+                    course.name, e
+                ),
+            }
+        }
+        let mut entries = match entries {
+            Some(entries) => entries,
+            None => self.get_course_rest(course).await?,
+        };
 
-    let runner = Runner::new(config);
-    let buf = runner.get_modules().await?;
-    let title_url_name = runner.fuzzy_find(&buf);
+        if self.config.group_by == Some(GroupBy::AssignmentGroup) {
+            let groups = self
+                .cached_list(
+                    course,
+                    List::AssignmentGroups,
+                    self.get_assignment_groups(course),
+                )
+                .await?;
+            for entry in &mut entries {
+                entry.assignment_group = entry
+                    .assignment_group_id
+                    .and_then(|id| groups.get(&id).cloned());
+            }
+        }
 
-    // get url from string
-    let mut it = title_url_name.split(" || ");
-    it.next();
-    let url = it.next().unwrap();
-    runner.open_link(url);
+        // first, since it's where most people start
+        if self.config.include_syllabus {
+            match self
+                .cached_list(course, List::Syllabus, self.get_syllabus(course))
+                .await
+            {
+                Ok(Some(syllabus)) => entries.insert(0, syllabus),
+                Ok(None) => {}
+                // not worth losing the rest of the course over
+                Err(e) => eprintln!(
+                    "warning: couldn't fetch {}'s syllabus: {:#}",
+                    course.name, e
+                ),
+            }
+        }
 
-    Ok(())
+        Ok(entries)
+    }
+
+    async fn get_course_rest(&self, course: &Course) -> Result<Vec<Entry>> {
+        // courses with the modules tab turned off either 403 or list nothing
+        let mut entries = match self
+            .cached_list(course, List::Modules, self.get_course_modules(course))
+            .await
+        {
+            Ok(entries) => entries,
+            Err(e) if self.config.pages_fallback && is_status(&e, StatusCode::FORBIDDEN) => {
+                Vec::new()
+            }
+            Err(e) => return Err(e),
+        };
+
+        let fallback =
+            self.config.pages_fallback && !self.config.include_pages && entries.is_empty();
+        if fallback {
+            eprintln!("{} has no modules, listing its pages instead", course.name);
+        }
+
+        // anything already linked from a module only shows up once, under its
+        // module item
+        if self.config.include_pages || fallback {
+            let pages = self
+                .cached_list(course, List::Pages, self.get_course_pages(course))
+                .await?;
+            entry::merge(&mut entries, pages);
+        }
+        if self.config.include_assignments {
+            let assignments = self
+                .cached_list(
+                    course,
+                    List::Assignments,
+                    self.get_course_assignments(course),
+                )
+                .await?;
+            entry::merge(&mut entries, assignments);
+        }
+        if self.config.include_announcements {
+            entries.extend(self.cached_announcements(course).await?);
+        }
+
+        Ok(entries)
+    }
+
+    async fn cached_announcements(&self, course: &Course) -> Result<Vec<Entry>> {
+        let announcements = self
+            .cached_list(
+                course,
+                List::Announcements,
+                self.get_course_announcements(course),
+            )
+            .await?;
+        if !self.config.announcements_digest {
+            return Ok(announcements);
+        }
+
+        let url = format!(
+            "{}/courses/{}/announcements",
+            self.config.canvas_api_url, course.id
+        );
+        Ok(entry::digest_announcements(announcements, url)
+            .into_iter()
+            .collect())
+    }
+
+    // one of a course's listings from its own cache, as long as that's within
+    // the listing's ttl, or else from `fetch`
+    async fn cached_list<T: serde::Serialize + serde::de::DeserializeOwned>(
+        &self,
+        course: &Course,
+        list: List,
+        fetch: impl std::future::Future<Output = Result<T>>,
+    ) -> Result<T> {
+        if let Some(entries) = self.fresh_list(course, list) {
+            return Ok(entries);
+        }
+
+        let entries = fetch.await?;
+        self.save_list(course, list, &entries)?;
+        Ok(entries)
+    }
+
+    // one of a course's listings from its cache, unless it's past its ttl
+    fn fresh_list<T: serde::de::DeserializeOwned>(&self, course: &Course, list: List) -> Option<T> {
+        let path = self.config.paths.list_cache(course.id, list.name());
+        if self.recache_all || !cache::is_fresh(&path, self.config.ttls.get(list)) {
+            return None;
+        }
+        cache::read_text(&path)
+            .ok()
+            .and_then(|cache| serde_json::from_str(&cache).ok())
+    }
+
+    fn save_list<T: serde::Serialize>(
+        &self,
+        course: &Course,
+        list: List,
+        entries: &T,
+    ) -> Result<()> {
+        let path = self.config.paths.list_cache(course.id, list.name());
+        std::fs::write(&path, serde_json::to_string(entries)?)?;
+        Ok(())
+    }
+
+    // a course's syllabus page, unless nothing's been written on it
+    async fn get_syllabus(&self, course: &Course) -> Result<Option<Entry>> {
+        let details = self
+            .get_json(
+                &format!("courses/{}", course.id),
+                &[("include[]", "syllabus_body")],
+            )
+            .await?;
+        let written = details["syllabus_body"]
+            .as_str()
+            .is_some_and(|body| !body.trim().is_empty());
+
+        Ok(written.then(|| Entry {
+            title: "Syllabus".to_string(),
+            url: format!(
+                "{}/courses/{}/assignments/syllabus",
+                self.config.canvas_api_url, course.id
+            ),
+            course: course.label().to_string(),
+            kind: EntryKind::Syllabus,
+            ..Default::default()
+        }))
+    }
+
+    // the names of a course's assignment groups, e.g. homework and exams, by id
+    async fn get_assignment_groups(&self, course: &Course) -> Result<HashMap<u64, String>> {
+        let groups = self
+            .get_paginated(
+                &format!("courses/{}/assignment_groups", course.id),
+                &[("per_page", "100")],
+            )
+            .await?;
+
+        Ok(groups
+            .iter()
+            .filter_map(|group| Some((group["id"].as_u64()?, group["name"].as_str()?.to_string())))
+            .collect())
+    }
+
+    // gets the title, url, and course name of every item in a single course's
+    // modules
+    async fn get_course_modules(&self, course: &Course) -> Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+
+        // get module page of the course
+        let modules = self
+            .get_paginated(
+                &format!("courses/{}/modules", course.id),
+                &[
+                    ("include[]", "items"),
+                    ("include[]", "content_details"),
+                    ("per_page", "100"),
+                ],
+            )
+            .await?;
+
+        // for every item (or dropdown menu in modules)
+        for module in modules {
+            let Some(pages) = module["items"].as_array() else {
+                continue;
+            };
+            for page in pages {
+                let kind =
+                    EntryKind::from_module_item_type(page["type"].as_str().unwrap_or_default());
+                let Some(title) = page["title"].as_str() else {
+                    continue;
+                };
+                // external tools may only have an lti launch `url`, which
+                // can't be opened directly, but the module item page launches
+                // the tool
+                let url = match (page["html_url"].as_str(), page["id"].as_u64()) {
+                    (Some(url), _) => url.to_string(),
+                    (None, Some(id)) if kind == EntryKind::ExternalTool => format!(
+                        "{}/courses/{}/modules/items/{}",
+                        self.config.canvas_api_url, course.id, id
+                    ),
+                    _ => continue,
+                };
+
+                entries.push(Entry {
+                    title: entry::single_line(title),
+                    url,
+                    course: course.label().to_string(),
+                    kind,
+                    // assignments, quizzes, and graded discussions carry a due
+                    // date in their content details
+                    due_at: parse_date(&page["content_details"]["due_at"]),
+                    page_slug: page["page_url"].as_str().map(str::to_string),
+                    content_id: page["content_id"].as_u64(),
+                    ..Default::default()
+                });
+            }
+        }
+        Ok(entries)
+    }
+
+    // gets everything `get_course` does, but with one graphql query for the
+    // modules, pages, and assignments. announcements still come from rest.
+    // what it gets back is cached in the same listings rest fills, so each
+    // keeps its own ttl, and the query is only made once any of them is stale
+    async fn fetch_course_graphql(&self, course: &Course) -> Result<Vec<Entry>> {
+        let pages = self.config.include_pages || self.config.pages_fallback;
+        let assignments = self.config.include_assignments;
+        let cached = || {
+            Some(graphql::CourseContent {
+                modules: self.fresh_list(course, List::Modules)?,
+                pages: match pages {
+                    true => self.fresh_list(course, List::Pages)?,
+                    false => Vec::new(),
+                },
+                assignments: match assignments {
+                    true => self.fresh_list(course, List::Assignments)?,
+                    false => Vec::new(),
+                },
+            })
+        };
+        let content = match cached() {
+            Some(content) => content,
+            None => {
+                self.query_course_graphql(course, pages, assignments)
+                    .await?
+            }
+        };
+
+        let mut entries = content.modules;
+        let fallback =
+            self.config.pages_fallback && !self.config.include_pages && entries.is_empty();
+        if fallback {
+            eprintln!("{} has no modules, listing its pages instead", course.name);
+        }
+        if self.config.include_pages || fallback {
+            entry::merge(&mut entries, content.pages);
+        }
+        entry::merge(&mut entries, content.assignments);
+        if self.config.include_announcements {
+            entries.extend(self.cached_announcements(course).await?);
+        }
+
+        Ok(entries)
+    }
+
+    async fn query_course_graphql(
+        &self,
+        course: &Course,
+        pages: bool,
+        assignments: bool,
+    ) -> Result<graphql::CourseContent> {
+        let res = self
+            .send(
+                self.client
+                    .post(format!("{}/api/graphql", &self.config.canvas_api_url))
+                    .json(&serde_json::json!({
+                        "query": graphql::COURSE_QUERY,
+                        "variables": {
+                            "id": course.id.to_string(),
+                            "pages": pages,
+                            "assignments": assignments,
+                        },
+                    })),
+            )
+            .await?;
+        let content = graphql::parse_course(
+            &http::read_json(res, self.config.max_response_size).await?,
+            course.label(),
+        )?;
+
+        self.save_list(course, List::Modules, &content.modules)?;
+        if pages {
+            self.save_list(course, List::Pages, &content.pages)?;
+        }
+        if assignments {
+            self.save_list(course, List::Assignments, &content.assignments)?;
+        }
+        Ok(content)
+    }
+
+    // gets every page in a course, whether or not it's in a module
+    async fn get_course_pages(&self, course: &Course) -> Result<Vec<Entry>> {
+        let pages = self
+            .get_paginated(
+                &format!("courses/{}/pages", course.id),
+                &[("sort", "title"), ("per_page", "100")],
+            )
+            .await?;
+
+        Ok(pages
+            .iter()
+            .filter_map(|page| {
+                Some(Entry {
+                    title: entry::single_line(page["title"].as_str()?),
+                    url: page["html_url"].as_str()?.to_string(),
+                    course: course.label().to_string(),
+                    kind: EntryKind::Page,
+                    recent_at: parse_date(&page["updated_at"]),
+                    front_page: page["front_page"].as_bool().unwrap_or(false),
+                    page_slug: Some(page["url"].as_str()?.to_string()),
+                    ..Default::default()
+                })
+            })
+            .collect())
+    }
+
+    // gets every assignment in a course, including quizzes
+    async fn get_course_assignments(&self, course: &Course) -> Result<Vec<Entry>> {
+        let assignments = self
+            .get_paginated(
+                &format!("courses/{}/assignments", course.id),
+                &[
+                    ("order_by", "due_at"),
+                    ("include[]", "submission"),
+                    ("per_page", "100"),
+                ],
+            )
+            .await?;
+
+        Ok(assignments
+            .iter()
+            .filter_map(|assignment| {
+                // quizzes are linked from modules by their quiz id
+                let (kind, content_id) = match assignment["quiz_id"].as_u64() {
+                    Some(quiz_id) => (EntryKind::Quiz, quiz_id),
+                    None => (EntryKind::Assignment, assignment["id"].as_u64()?),
+                };
+                Some(Entry {
+                    title: entry::single_line(assignment["name"].as_str()?),
+                    url: assignment["html_url"].as_str()?.to_string(),
+                    course: course.label().to_string(),
+                    kind,
+                    due_at: parse_date(&assignment["due_at"]),
+                    recent_at: parse_date(&assignment["updated_at"]),
+                    content_id: Some(content_id),
+                    submission: entry::Submission::from_json(&assignment["submission"]),
+                    assignment_group_id: assignment["assignment_group_id"].as_u64(),
+                    ..Default::default()
+                })
+            })
+            .collect())
+    }
+
+    // gets every announcement posted in a course
+    async fn get_course_announcements(&self, course: &Course) -> Result<Vec<Entry>> {
+        let announcements = self
+            .get_paginated(
+                &format!("courses/{}/discussion_topics", course.id),
+                &[("only_announcements", "true"), ("per_page", "100")],
+            )
+            .await?;
+
+        Ok(announcements
+            .iter()
+            .filter_map(|announcement| {
+                Some(Entry {
+                    title: entry::single_line(announcement["title"].as_str()?),
+                    url: announcement["html_url"].as_str()?.to_string(),
+                    course: course.label().to_string(),
+                    kind: EntryKind::Announcement,
+                    recent_at: parse_date(&announcement["posted_at"]),
+                    content_id: announcement["id"].as_u64(),
+                    unread: announcement["read_state"] == "unread",
+                    ..Default::default()
+                })
+            })
+            .collect())
+    }
+
+    // the text of a page for fzf's preview window, or just the fields of the
+    // line for anything that isn't a page
+    async fn preview(&self, line: &str) -> Result<String> {
+        let Some(selection) = entry::parse_selection(line, entry::SEPARATOR) else {
+            return Ok(line.to_string());
+        };
+
+        // module items link to the module rather than the page, so which page
+        // they are comes from the cached entry
+        let entry = self.cached_entry(&selection.url).unwrap_or(selection);
+
+        if let Some((course_id, slug)) = preview::page_target(&entry) {
+            let text = self.get_page_text(&course_id, &slug).await?;
+            return Ok(format!("{}\n\n{}", entry.title, text));
+        }
+
+        Ok(format!(
+            "{}\n{}\n{}\n",
+            entry.title, entry.course, entry.url
+        ))
+    }
+
+    // whatever was fetched last for these courses, however old it is, to
+    // start `--stream` on
+    fn stale_entries(&self) -> (Vec<Entry>, cache::Age) {
+        cache::read_text(&self.config.paths.entries_cache())
+            .ok()
+            .and_then(|cache| serde_json::from_str::<EntriesCache>(&cache).ok())
+            .filter(|cache| cache.key == self.cache_key())
+            .map(|cache| {
+                let age = cache::Age::of(&cache);
+                (cache.entries, age)
+            })
+            .unwrap_or((Vec::new(), cache::Age::Unknown))
+    }
+
+    // looks up an entry from the last fetch, however old it is
+    fn cached_entry(&self, url: &str) -> Option<Entry> {
+        let cache = cache::read_text(&self.config.paths.entries_cache()).ok()?;
+        let cache: EntriesCache = serde_json::from_str(&cache).ok()?;
+        cache.entries.into_iter().find(|entry| entry.url == url)
+    }
+
+    // the text of a page's body, cached so previews scroll quickly
+    async fn get_page_text(&self, course_id: &str, slug: &str) -> Result<String> {
+        let cache_path = self.config.paths.preview(course_id, slug);
+        if cache::is_fresh(&cache_path, self.config.ttls.pages) {
+            if let Ok(text) = cache::read_text(&cache_path) {
+                return Ok(text);
+            }
+        }
+
+        let page = self
+            .get_json(&format!("courses/{}/pages/{}", course_id, slug), &[])
+            .await?;
+        let text = preview::html_to_text(page["body"].as_str().unwrap_or_default());
+
+        std::fs::write(&cache_path, &text)?;
+        Ok(text)
+    }
+
+    // fetches the previews of the first `limit` pages, e.g. while the finder
+    // is open so the first screen of previews shows up instantly. returns how
+    // many were fetched
+    async fn prefetch_previews(&self, entries: &[Entry], limit: usize) -> usize {
+        let targets = entries.iter().filter_map(preview::page_target).take(limit);
+
+        futures::stream::iter(targets)
+            .map(|(course_id, slug)| async move {
+                // a preview that fails here just gets fetched again when it's
+                // shown
+                self.get_page_text(&course_id, &slug).await.is_ok()
+            })
+            .buffer_unordered(PREFETCH_CONCURRENCY)
+            .filter(|&fetched| async move { fetched })
+            .count()
+            .await
+    }
+
+    // refetches every enabled content type for every course, and every page
+    // preview, so that later runs (including `--offline` ones) have it all
+    async fn warm(&mut self) -> Result<()> {
+        let started = std::time::Instant::now();
+        self.recache_all = true;
+
+        let (entries, _) = self.get_modules(None).await?;
+        let courses: std::collections::HashSet<&str> =
+            entries.iter().map(|entry| entry.course.as_str()).collect();
+
+        let pages = entries
+            .iter()
+            .filter(|entry| preview::page_target(entry).is_some())
+            .count();
+        let previews = self.prefetch_previews(&entries, usize::MAX).await;
+
+        println!(
+            "warmed {} entries from {} of {} courses and {} of {} page previews in {:.1}s",
+            entries.len(),
+            courses.len(),
+            self.config.courses.len(),
+            previews,
+            pages,
+            started.elapsed().as_secs_f64()
+        );
+        Ok(())
+    }
+}
+
+mod windows {
+    use std::process::Command;
+
+    use std::path::Path;
+
+    use anyhow::{Context, Result};
+
+    use crate::Config;
+
+    pub async fn fuzzy_finder(
+        config: &Config,
+        finder: &Path,
+        str: &str,
+        fzf_args: &[String],
+    ) -> Result<String> {
+        let paths = &config.paths;
+
+        // write buffer to the cache directory
+        std::fs::write(paths.buf(), str)?;
+        // so a finder that never ran can't leave the last pick behind
+        crate::clear_selection(config)?;
+
+        // run fzf through powershell so the selection is written as UTF-8.
+        // the previews it runs need the token too, which might not have come
+        // from `TOKEN`
+        let status = tokio::process::Command::new("pwsh")
+            .env("TOKEN", &config.token)
+            .arg("-File")
+            .arg(paths.ps1_script()?)
+            .arg(paths.buf())
+            .arg(paths.selection())
+            .arg(finder)
+            .args(fzf_args)
+            .status()
+            .await
+            .context("couldn't open the finder")?;
+
+        crate::read_selection(config, status)
+    }
+    pub fn open_link(url: &str) {
+        Command::new("explorer").arg(url).output().unwrap();
+    }
+}
+
+mod macos {
+    use std::process::Command;
+
+    use std::path::Path;
+
+    use anyhow::{Context, Result};
+
+    use crate::Config;
+
+    pub async fn fuzzy_finder(
+        config: &Config,
+        finder: &Path,
+        str: &str,
+        fzf_args: &[String],
+    ) -> Result<String> {
+        let paths = &config.paths;
+
+        // write buffer to the cache directory
+        std::fs::write(paths.buf(), str)?;
+        crate::clear_selection(config)?;
+
+        // Open kitty with fzf
+        let status = tokio::process::Command::new("kitty")
+            .env("TOKEN", &config.token)
+            .arg("sh")
+            .arg(paths.sh_script()?)
+            .arg(paths.buf())
+            .arg(paths.selection())
+            .arg(finder)
+            .args(fzf_args)
+            .status()
+            .await
+            .context("couldn't open the finder")?;
+
+        crate::read_selection(config, status)
+    }
+    pub fn open_link(url: &str) {
+        Command::new("open").arg(url).output().unwrap();
+    }
+}
+
+mod linux {
+    use std::process::Command;
+
+    use std::path::Path;
+
+    use anyhow::{Context, Result};
+
+    use crate::Config;
+
+    pub async fn fuzzy_finder(
+        config: &Config,
+        finder: &Path,
+        str: &str,
+        fzf_args: &[String],
+    ) -> Result<String> {
+        let paths = &config.paths;
+
+        // write buffer to the cache directory
+        std::fs::write(paths.buf(), str)?;
+        crate::clear_selection(config)?;
+
+        // Open kitty with fzf
+        let status = tokio::process::Command::new("kitty")
+            .env("TOKEN", &config.token)
+            .arg("sh")
+            .arg(paths.sh_script()?)
+            .arg(paths.buf())
+            .arg(paths.selection())
+            .arg(finder)
+            .args(fzf_args)
+            .status()
+            .await
+            .context("couldn't open the finder")?;
+
+        crate::read_selection(config, status)
+    }
+    pub fn open_link(url: &str) {
+        Command::new("xdg-open").arg(url).output().unwrap();
+    }
+}
+
+// removes the last run's pick before the finder's opened
+fn clear_selection(config: &Config) -> Result<()> {
+    let selection = config.paths.selection();
+    match std::fs::remove_file(&selection) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("couldn't remove {}", selection.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+// what was picked once the finder's terminal has closed. the script always
+// writes the selection, even an empty one when nothing was picked, so without
+// it the finder never ran. fzf exits with 2 on errors of its own
+fn read_selection(config: &Config, status: std::process::ExitStatus) -> Result<String> {
+    let selection = config.paths.selection();
+    if !selection.exists() || status.code() == Some(2) {
+        anyhow::bail!("the finder couldn't be run ({})", status);
+    }
+    Ok(cache::read_text(&selection)?.trim().to_string())
+}
+
+// the flags a run was given that the previews it starts have to make the same
+// requests with. the token's handed to them through `TOKEN` instead, since
+// it'd be seen in their arguments
+fn preview_flags(args: &Args) -> Vec<String> {
+    let mut flags = Vec::new();
+    if let Some(timeout) = args.timeout {
+        flags.extend(["--timeout".to_string(), timeout.to_string()]);
+    }
+    if let Some(proxy) = &args.proxy {
+        flags.extend(["--proxy".to_string(), proxy.clone()]);
+    }
+    if let Some(ca_bundle) = &args.ca_bundle {
+        flags.extend([
+            "--ca-bundle".to_string(),
+            ca_bundle.to_string_lossy().into_owned(),
+        ]);
+    }
+    if args.accept_invalid_certs {
+        flags.push("--accept-invalid-certs".to_string());
+    }
+    for header in &args.headers {
+        flags.extend(["--header".to_string(), header.clone()]);
+    }
+    for param in &args.query_params {
+        flags.extend(["--query-param".to_string(), param.clone()]);
+    }
+    flags
+}
+
+// quotes an argument for the shell fzf runs its preview command in
+fn shell_quote(arg: &str) -> String {
+    match cfg!(windows) {
+        true => format!("\"{}\"", arg.replace('"', "\\\"")),
+        false => format!("'{}'", arg.replace('\'', "'\\''")),
+    }
+}
+
+// fails before anything's fetched if the finder, or the terminal it's opened
+// in, isn't installed, rather than once the fetch is done
+fn check_finder_available(config: &Config) -> Result<()> {
+    let terminal = match config.os {
+        OS::Windows => "pwsh",
+        OS::MacOS | OS::Linux => "kitty",
+    };
+    if find_executable(terminal).is_none() {
+        anyhow::bail!("{} not found, install it to open the finder in", terminal);
+    }
+    if find_executable(&config.finder_bin).is_none() {
+        anyhow::bail!(
+            "{} not found, install it or point `--finder-bin` at it",
+            config.finder_bin
+        );
+    }
+    Ok(())
+}
+
+// where a program would be run from, either a path to it or a name looked up
+// on `PATH`
+fn find_executable(name: &str) -> Option<PathBuf> {
+    let path = Path::new(name);
+    if path.components().count() > 1 {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+
+    let exts: &[&str] = if cfg!(windows) {
+        &["", ".exe", ".cmd", ".bat"]
+    } else {
+        &[""]
+    };
+    std::env::split_paths(&std::env::var_os("PATH")?).find_map(|dir| {
+        exts.iter()
+            .map(|ext| dir.join(format!("{}{}", name, ext)))
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+// a setting from a flag, falling back to an environment variable and then the
+// config file
+fn layered<T>(flag: Option<T>, var: &str, file: Option<T>) -> Result<Option<T>>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    if flag.is_some() {
+        return Ok(flag);
+    }
+    match std::env::var(var) {
+        Ok(s) => s
+            .parse()
+            .map(Some)
+            .map_err(|e| anyhow::anyhow!("invalid `{}` `{}`: {}", var, s, e)),
+        Err(_) => Ok(file),
+    }
+}
+
+// canvas dates are iso 8601 strings, or null
+fn parse_date(value: &serde_json::Value) -> Option<chrono::DateTime<chrono::Utc>> {
+    value.as_str().and_then(|s| s.parse().ok())
+}
+
+// whether an error came from a response with the given status
+fn is_status(e: &anyhow::Error, status: StatusCode) -> bool {
+    e.downcast_ref::<reqwest::Error>()
+        .and_then(|e| e.status())
+        .is_some_and(|s| s == status)
+}
+
+// everything known about an entry, one field per line
+fn print_entry(entry: &Entry) {
+    println!("  title: {}", entry.title);
+    println!("  url: {}", entry.url);
+    println!("  course: {}", entry.course);
+    println!("  kind: {:?}", entry.kind);
+    if let Some(due_at) = entry.due_at {
+        println!("  due: {}", due_at.to_rfc3339());
+    }
+    if let Some(recent_at) = entry.recent_at {
+        println!("  updated: {}", recent_at.to_rfc3339());
+    }
+    if entry.front_page {
+        println!("  front page: true");
+    }
+}
+
+// what was opened, on one line, e.g. `opened Homework 1 (CS 101, Assignment,
+// due in 2d)`
+fn summarize(entry: &Entry, date_format: DateFormat) -> String {
+    let mut details = vec![entry.course.clone(), format!("{:?}", entry.kind)];
+    if let Some(due_at) = entry.due_at {
+        details.push(format!(
+            "due {}",
+            date_format.format(due_at, chrono::Utc::now())
+        ));
+    }
+    format!("opened {} ({})", entry.title, details.join(", "))
+}
+
+// asks a yes or no question on the terminal, defaulting to yes. without a
+// terminal to ask on, the answer is yes
+fn confirm(question: &str) -> Result<bool> {
+    if !std::io::stdin().is_terminal() {
+        return Ok(true);
+    }
+
+    eprint!("{} [Y/n] ", question);
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(!answer.trim().to_lowercase().starts_with('n'))
+}
+
+// removes everything kept in the state dir, leaving the cache alone
+fn reset_state(paths: &Paths) -> Result<()> {
+    let mut cleared = 0;
+    for path in paths.state_files() {
+        match std::fs::remove_file(&path) {
+            Ok(()) => {
+                println!("cleared {}", path.display());
+                cleared += 1;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e).with_context(|| format!("couldn't remove {}", path.display())),
+        }
+    }
+    if cleared == 0 {
+        println!("no state to clear in {}", paths.state.display());
+    }
+    Ok(())
+}
+
+// deletes the cached listings and previews of courses that aren't in
+// `courses`, so they can't come back if the course is searched again later
+fn prune_cache(paths: &Paths, courses: &[Course]) -> Result<()> {
+    let mut pruned = 0;
+    for (course_id, path) in paths.course_caches()? {
+        if courses.iter().any(|course| course.id == course_id) {
+            continue;
+        }
+        std::fs::remove_file(&path)
+            .with_context(|| format!("couldn't remove {}", path.display()))?;
+        println!("pruned {}", path.display());
+        pruned += 1;
+    }
+    if pruned == 0 {
+        println!("nothing to prune in {}", paths.cache.display());
+    }
+    Ok(())
+}
+
+// prints whether a check passed, and how to fix it if it didn't
+fn report(passed: bool, what: &str, hint: &str) -> bool {
+    if passed {
+        println!("ok    {}", what);
+    } else {
+        println!("FAIL  {}", what);
+        println!("      {}", hint);
+    }
+    passed
+}
+
+// checks everything a search needs, one at a time, failing if anything
+// would stop one. nothing is changed, other than creating the directories
+// any run would
+async fn doctor(args: &Args) -> Result<()> {
+    let config = match Config::new(args) {
+        Ok(config) => config,
+        Err(e) => {
+            report(
+                false,
+                &format!("config: {:#}", e),
+                "check the `.env` and `config.toml` in the config dir",
+            );
+            anyhow::bail!("the config has to load for anything else to be checked");
+        }
+    };
+    report(true, "config loaded", "");
+    let mut checks = vec![true];
+
+    // without retries, since a check shouldn't take half a minute to fail
+    let mut runner = Runner::new(Config {
+        max_retries: 0,
+        ..config
+    })?;
+    let api_url = runner.config.canvas_api_url.clone();
+    match runner.set_user_id().await {
+        Ok(()) => {
+            checks.push(report(true, &format!("reached {}", api_url), ""));
+            checks.push(report(true, "token verified", ""));
+        }
+        // tokens scoped to courses aren't allowed to look themselves up
+        Err(e) if is_status(&e, StatusCode::FORBIDDEN) => {
+            checks.push(report(true, &format!("reached {}", api_url), ""));
+            checks.push(report(
+                true,
+                "token accepted, but can't see its own user (403), so user-scoped features are skipped",
+                "",
+            ));
+        }
+        Err(e) if is_status(&e, StatusCode::UNAUTHORIZED) => {
+            checks.push(report(true, &format!("reached {}", api_url), ""));
+            checks.push(report(
+                false,
+                "token rejected (401)",
+                "generate a new one in canvas under account > settings > new access token, and set `TOKEN`",
+            ));
+        }
+        Err(e) => {
+            checks.push(report(
+                false,
+                &format!("couldn't reach {}: {:#}", api_url, e),
+                "check `CANVAS_API_URL` is your school's canvas, e.g. https://canvas.someuniversity.edu, and any `[network]` settings",
+            ));
+        }
+    }
+
+    let finder = check_finder_available(&runner.config);
+    checks.push(report(
+        finder.is_ok(),
+        &match &finder {
+            Ok(()) => format!("finder `{}` found", runner.config.finder_bin),
+            Err(e) => format!("finder: {:#}", e),
+        },
+        "or use `--on-no-finder print` or `--on-no-finder inline` to go without it",
+    ));
+
+    // a file that's written and removed straight away
+    let cache = &runner.config.paths.cache;
+    let probe = cache.join(format!(".doctor.{}", std::process::id()));
+    let writable = std::fs::write(&probe, "").and_then(|()| std::fs::remove_file(&probe));
+    checks.push(report(
+        writable.is_ok(),
+        &match &writable {
+            Ok(()) => format!("cache dir {} is writable", cache.display()),
+            Err(e) => format!("cache dir {} isn't writable: {}", cache.display(), e),
+        },
+        "fix its permissions, or point `CACHE_DIR` somewhere writable",
+    ));
+
+    let failed = checks.iter().filter(|&&passed| !passed).count();
+    if failed > 0 {
+        anyhow::bail!("{} of {} checks failed", failed, checks.len());
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    // a current-thread runtime skips spinning up a worker thread per core,
+    // which is all wasted on a run that's over in a second or two
+    let runtime = if args.single_thread {
+        tokio::runtime::Builder::new_current_thread()
+    } else {
+        tokio::runtime::Builder::new_multi_thread()
+    }
+    .enable_all()
+    .build()?;
+
+    runtime.block_on(run(args))
+}
+
+async fn run(args: Args) -> Result<()> {
+    // doesn't need a token, so it works even after switching accounts
+    if let Some(Command::ResetState) = &args.command {
+        Config::load_env()?;
+        return reset_state(&Paths::new()?);
+    }
+
+    // reports a config that doesn't load rather than stopping at it
+    if let Some(Command::Doctor) = &args.command {
+        return doctor(&args).await;
+    }
+
+    let config = Config::new(&args)?;
+
+    let mut runner = Runner::new(config)?;
+
+    match &args.command {
+        Some(Command::Preview { line }) => {
+            print!("{}", runner.preview(line).await?);
+            return Ok(());
+        }
+        Some(Command::ListCourses) => return runner.list_courses().await,
+        Some(Command::ResetState) | Some(Command::Doctor) => {
+            unreachable!("handled before the config is loaded")
+        }
+        Some(Command::Warm) => {
+            runner.resolve_courses().await?;
+            return runner.warm().await;
+        }
+        Some(Command::PruneCache) => {
+            // `--course` only narrows down one search, so those courses are
+            // still kept
+            runner.config.course_filter = None;
+            runner.resolve_courses().await?;
+            return prune_cache(&runner.config.paths, &runner.config.courses);
+        }
+        None => {}
+    }
+
+    if runner.config.open_title.is_none() && !runner.config.safe {
+        if let Err(e) = check_finder_available(&runner.config) {
+            runner.config.without_finder(e)?;
+        }
+    }
+
+    if !runner.config.offline {
+        runner.look_up_user().await;
+
+        runner.resolve_courses().await?;
+    }
+
+    let mut history = History::load(&runner.config.paths.history());
+
+    let course_colors = runner
+        .config
+        .color
+        .then(|| color::course_colors(&runner.config.courses));
+
+    let (lines, selections) = loop {
+        // `--stream` fetches once the finder's open
+        let (mut entries, age) = match runner.config.stream {
+            true => runner.stale_entries(),
+            false => runner.get_modules(None).await?,
+        };
+        entries.retain(|entry| !runner.is_hidden(entry));
+        if runner.config.only_new {
+            let previous = cache::read_text(&runner.config.paths.previous_entries())
+                .ok()
+                .and_then(|cache| serde_json::from_str::<EntriesCache>(&cache).ok())
+                .map(|cache| cache.entries)
+                .unwrap_or_default();
+            entries = entry::new_since(entries, &previous);
+            if entries.is_empty() {
+                eprintln!("nothing is new since the cache was last refetched");
+                return Ok(());
+            }
+        }
+        // `--sort recent` already puts everything in an order of its own
+        if runner.config.sort == Sort::Course && !runner.config.kind_priority.is_empty() {
+            entry::sort_by_kind_priority(&mut entries, &runner.config.kind_priority);
+        }
+        if runner.config.front_page_first {
+            entry::front_pages_first(&mut entries);
+        }
+        if runner.config.sort == Sort::Recent {
+            entry::sort_by_recency(&mut entries);
+        }
+        if runner.config.group_by == Some(GroupBy::AssignmentGroup) {
+            entry::group_by_assignment_group(&mut entries);
+        }
+        let render_options = RenderOptions {
+            date_format: runner.config.date_format,
+            history: runner.config.show_recency.then_some(&history),
+            course_colors: None,
+            status_icons: &runner.config.status_icons,
+            color: false,
+            urgency: false,
+            compact: false,
+            truncate: None,
+        };
+
+        if runner.config.print_entries {
+            print!("{}", entry::render(&entries, &render_options)?);
+            return Ok(());
+        }
+
+        let (lines, selections) = if let Some(title) = &runner.config.open_title {
+            // scripted runs pick by title and skip the finder entirely
+            match entry::find_by_title(&entries, title).as_slice() {
+                [] => anyhow::bail!("no entry is titled `{}`", title),
+                [entry] => {
+                    let entry = (*entry).clone();
+                    let line = entry::render(std::slice::from_ref(&entry), &render_options)?;
+                    (vec![line.trim_end().to_string()], vec![entry])
+                }
+                matches => {
+                    let matches: Vec<Entry> = matches.iter().map(|&e| e.clone()).collect();
+                    anyhow::bail!(
+                        "{} entries are titled `{}`, narrow it down with `--course`:\n{}",
+                        matches.len(),
+                        title,
+                        entry::render(&matches, &render_options)?.trim_end()
+                    );
+                }
+            }
+        } else {
+            let mut shown = entries.clone();
+            let truncated = runner.config.max_entries.and_then(|max| {
+                match entry::cap(&mut shown, max, runner.config.sort == Sort::Course) {
+                    0 => None,
+                    dropped => Some(format!(
+                        "(truncated) showing {} of {} entries",
+                        shown.len(),
+                        shown.len() + dropped
+                    )),
+                }
+            });
+
+            let cache_age = match age {
+                cache::Age::Fresh => Some("cache: fresh".to_string()),
+                cache::Age::FetchedAt(fetched_at) => Some(format!(
+                    "cache: fetched {}",
+                    entry::format_relative(fetched_at - chrono::Utc::now())
+                )),
+                cache::Age::Unknown => None,
+            };
+            let updating = runner.config.stream.then(|| "updating".to_string());
+            let header = [cache_age, updating, truncated]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join("  |  ");
+
+            // only the finder is told to expect colors
+            let finder_options = RenderOptions {
+                course_colors: course_colors.as_ref(),
+                color: runner.config.color,
+                urgency: true,
+                compact: runner.config.compact,
+                truncate: runner.config.truncate,
+                ..render_options
+            };
+            let buf = entry::render(&shown, &finder_options)?;
+            let header = Some(header.as_str()).filter(|h| !h.is_empty());
+
+            let streamed = std::cell::RefCell::new(Vec::new());
+            let picked = if runner.config.stream {
+                runner
+                    .stream_find(&buf, header, &entries, &finder_options, &streamed)
+                    .await
+            } else {
+                // once something's picked there's no point finishing the
+                // prefetch
+                let finder = runner.fuzzy_find(&buf, header);
+                tokio::pin!(finder);
+                tokio::select! {
+                    title_url_name = &mut finder => title_url_name,
+                    _ = runner.prefetch_previews(&shown, runner.config.prefetch_previews) => finder.await,
+                }
+            };
+            // the terminal can be installed and still not open, e.g. without
+            // a display, in which case it's picked from some other way
+            let title_url_name = match picked {
+                Ok(title_url_name) => title_url_name,
+                Err(e) if !runner.config.inline_picker && !runner.config.safe => {
+                    runner.config.without_finder(e)?;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            let streamed = streamed.into_inner();
+
+            // one line per pick with `--multi`, and nothing was picked if
+            // the finder was closed
+            let (lines, selections): (Vec<_>, Vec<_>) = title_url_name
+                .lines()
+                .filter_map(|line| {
+                    let selection = entry::parse_selection(line, entry::SEPARATOR)?;
+                    // the line only has some of what's known about the entry
+                    let selection = entries
+                        .iter()
+                        .chain(&streamed)
+                        .find(|entry| entry.url == selection.url)
+                        .cloned()
+                        .unwrap_or(selection);
+                    Some((line.to_string(), selection))
+                })
+                .unzip();
+            if selections.is_empty() {
+                return Ok(());
+            }
+            (lines, selections)
+        };
+
+        // a cached entry can point at a page that's since been moved or
+        // deleted, in which case the list is refetched and picked from again.
+        // once it's been refetched there's nothing fresher to offer
+        if runner.config.verify_before_open && !runner.config.offline && !runner.recache_all {
+            let mut dead = None;
+            for selection in &selections {
+                if runner.is_dead_link(&selection.url).await {
+                    dead = Some(&selection.url);
+                    break;
+                }
+            }
+            if let Some(url) = dead {
+                if confirm(&format!(
+                    "{} no longer exists, refresh the cache and pick again?",
+                    url
+                ))? {
+                    runner.recache_all = true;
+                    continue;
+                }
+            }
+        }
+
+        break (lines, selections);
+    };
+
+    if runner.config.no_open {
+        for (line, selection) in lines.iter().zip(&selections) {
+            println!("{}", entry::unpad(line));
+            print_entry(selection);
+        }
+        return Ok(());
+    }
+
+    let mut downloads = Downloads::load(&runner.config.paths.downloads());
+
+    for (i, selection) in selections.iter().enumerate() {
+        let url = &selection.url;
+        if runner.config.download {
+            match (selection.kind, selection.content_id) {
+                (EntryKind::File, Some(file_id)) => {
+                    let local = runner.download_file(file_id).await?;
+                    downloads.record(url, local);
+                    downloads.save(&runner.config.paths.downloads())?;
+                }
+                _ => eprintln!("warning: {} isn't a file, opening it instead", url),
+            }
+        }
+
+        // with `--new-window`, the first link gets a window of its own and
+        // the rest open as tabs in it
+        let placement = if runner.config.new_window && i == 0 {
+            Placement::Window
+        } else {
+            Placement::Tab
+        };
+        match downloads.get(url) {
+            Some(local) if runner.config.offline || runner.config.download => {
+                runner.open_local(&local.to_string_lossy())?
+            }
+            _ => runner.open_link(url, placement).await?,
+        }
+
+        if runner.config.summary {
+            eprintln!("{}", summarize(selection, runner.config.date_format));
+        }
+        history.record(url);
+    }
+    history.save(&runner.config.paths.history())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a runner answering from a recording of `users/self` being forbidden,
+    // with everything it'd write kept in a temporary directory
+    fn forbidden_runner(dir: &Path) -> Runner {
+        let recording = dir.join("recording");
+        std::fs::create_dir_all(&recording).unwrap();
+        let interaction = serde_json::json!({
+            "method": "GET",
+            "url": "http://canvas.test/api/v1/users/self",
+            "status": 403,
+            "headers": [],
+        });
+        std::fs::write(recording.join("00000.json"), interaction.to_string()).unwrap();
+        std::fs::write(recording.join("00000.body"), "{}").unwrap();
+
+        std::env::set_var("TOKEN", "token");
+        std::env::set_var("CANVAS_API_URL", "http://canvas.test");
+        std::env::set_var("COURSE_IDS", "1");
+        for var in ["CONFIG_DIR", "DATA_DIR", "CACHE_DIR", "STATE_DIR"] {
+            std::env::set_var(var, dir.join(var));
+        }
+        let args = Args::parse_from([
+            "canvas-fuzzy-finder".as_ref(),
+            "--replay".as_ref(),
+            recording.as_os_str(),
+        ]);
+        let mut config = Config::new(&args).unwrap();
+        config.max_retries = 0;
+        Runner::new(config).unwrap()
+    }
+
+    #[tokio::test]
+    async fn forbidden_user_lookup_isnt_fatal() {
+        let dir = std::env::temp_dir().join(format!("cff-forbidden-{}", std::process::id()));
+        let mut runner = forbidden_runner(&dir);
+
+        let e = runner.set_user_id().await.unwrap_err();
+        assert!(is_status(&e, StatusCode::FORBIDDEN));
+
+        runner.look_up_user().await;
+        assert_eq!(runner.user_id, None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
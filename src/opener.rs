@@ -0,0 +1,161 @@
+use std::process::Command;
+
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::OS;
+
+// browsers that can be told which profile to open a link in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Browser {
+    Chrome,
+    Chromium,
+    Edge,
+    Brave,
+    Firefox,
+}
+
+impl Browser {
+    // what it's run as on linux, or the app name on macos, or what `start`
+    // knows it as on windows
+    fn program(&self, os: &OS) -> &'static str {
+        match (self, os) {
+            (Browser::Chrome, OS::Linux) => "google-chrome",
+            (Browser::Chrome, OS::MacOS) => "Google Chrome",
+            (Browser::Chrome, OS::Windows) => "chrome",
+            (Browser::Chromium, OS::MacOS) => "Chromium",
+            (Browser::Chromium, _) => "chromium",
+            (Browser::Edge, OS::Linux) => "microsoft-edge",
+            (Browser::Edge, OS::MacOS) => "Microsoft Edge",
+            (Browser::Edge, OS::Windows) => "msedge",
+            (Browser::Brave, OS::Linux) => "brave-browser",
+            (Browser::Brave, OS::MacOS) => "Brave Browser",
+            (Browser::Brave, OS::Windows) => "brave",
+            (Browser::Firefox, OS::MacOS) => "Firefox",
+            (Browser::Firefox, _) => "firefox",
+        }
+    }
+
+    fn profile_args(&self, profile: &str) -> Vec<String> {
+        match self {
+            Browser::Firefox => vec!["-P".to_string(), profile.to_string()],
+            _ => vec![format!("--profile-directory={}", profile)],
+        }
+    }
+
+    // chromium-based browsers open links in a tab of the last used window
+    // without being asked, while firefox has to be told which
+    fn placement_args(&self, placement: Placement) -> Vec<String> {
+        match (self, placement) {
+            (Browser::Firefox, Placement::Window) => vec!["-new-window".to_string()],
+            (Browser::Firefox, Placement::Tab) => vec!["-new-tab".to_string()],
+            (_, Placement::Window) => vec!["--new-window".to_string()],
+            (_, Placement::Tab) => vec![],
+        }
+    }
+}
+
+// where a link opens in the browser
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    Window,
+    Tab,
+}
+
+// opens links in a particular browser, and profile of it, instead of the
+// system default
+#[derive(Debug)]
+pub struct Opener {
+    pub browser: Browser,
+    // runs this instead of the browser's usual name, e.g. a full path
+    pub command: Option<String>,
+    pub profile: Option<String>,
+    // the browser's devtools port, which it's started with when it isn't
+    // already listening on it
+    pub remote_port: Option<u16>,
+}
+
+impl Opener {
+    // opens a tab through the devtools protocol of a browser that's already
+    // running with `remote_port`. the link is the whole query, encoded so
+    // its own query and fragment come through with it
+    pub async fn open_remote(&self, port: u16, url: &str) -> anyhow::Result<()> {
+        let url: String = url::form_urlencoded::byte_serialize(url.as_bytes()).collect();
+        reqwest::Client::new()
+            .put(format!("http://127.0.0.1:{}/json/new?{}", port, url))
+            .timeout(std::time::Duration::from_secs(2))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    pub fn command(&self, os: &OS, url: &str, placement: Placement) -> Command {
+        let program = self
+            .command
+            .as_deref()
+            .unwrap_or_else(|| self.browser.program(os));
+        let mut args = self
+            .profile
+            .as_deref()
+            .map(|profile| self.browser.profile_args(profile))
+            .unwrap_or_default();
+        if let Some(port) = self.remote_port {
+            args.push(format!("--remote-debugging-port={}", port));
+        }
+        args.extend(self.browser.placement_args(placement));
+        args.push(url.to_string());
+
+        match os {
+            OS::Linux => {
+                let mut command = Command::new(program);
+                command.args(args);
+                command
+            }
+            // a new instance, since arguments are dropped when the app is
+            // already open
+            OS::MacOS => {
+                let mut command = Command::new("open");
+                command.args(["-na", program, "--args"]).args(args);
+                command
+            }
+            // `start` finds browsers that aren't on `PATH`
+            OS::Windows => {
+                let mut command = Command::new("cmd");
+                command.args(["/C", "start", "", program]).args(args);
+                command
+            }
+        }
+    }
+}
+
+// what `--flatten-external` strips when `strip_params` isn't set
+pub const DEFAULT_STRIP_PARAMS: [&str; 2] = ["utm_*", "session_token"];
+
+// removes the query parameters matching any of `patterns`, which are names or
+// prefixes ending in `*`, e.g. `utm_*`. urls that don't parse are left as is
+pub fn strip_params(url: &str, patterns: &[String]) -> String {
+    let Ok(mut parsed) = reqwest::Url::parse(url) else {
+        return url.to_string();
+    };
+    let matches = |name: &str| {
+        patterns
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => name == pattern,
+            })
+    };
+    let kept: Vec<(String, String)> = parsed
+        .query_pairs()
+        .filter(|(name, _)| !matches(name))
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    if kept.is_empty() {
+        parsed.set_query(None);
+    } else {
+        parsed.query_pairs_mut().clear().extend_pairs(kept);
+    }
+    parsed.to_string()
+}
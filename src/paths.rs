@@ -0,0 +1,328 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::{Context, Result};
+
+const APP_NAME: &str = "canvas-fuzzy-finder";
+
+const SH_SCRIPT: &str = include_str!("../fzf-to-title-url-name.sh");
+const PS1_SCRIPT: &str = include_str!("../fzf-to-title-url-name.ps1");
+
+// where everything the finder reads and writes lives, apart from the `.env`
+// in the config dir
+//
+// - `data` holds the finder scripts and downloaded files
+// - `cache` holds fetched responses and the buffer handed to the finder
+// - `state` holds things that should outlive the cache, like history
+#[derive(Debug)]
+pub struct Paths {
+    pub data: PathBuf,
+    pub cache: PathBuf,
+    pub state: PathBuf,
+}
+
+impl Paths {
+    // the config dir can only be overridden by a real environment variable,
+    // since it's where the `.env` is looked up
+    pub fn config_dir() -> Result<PathBuf> {
+        dir_from("CONFIG_DIR", dirs::config_dir())
+    }
+
+    // reads overrides from `DATA_DIR`, `CACHE_DIR`, and `STATE_DIR`, falling
+    // back to the platform's (XDG on linux) base directories
+    pub fn new() -> Result<Self> {
+        let paths = Self {
+            data: dir_from("DATA_DIR", dirs::data_dir())?,
+            cache: dir_from("CACHE_DIR", dirs::cache_dir())?,
+            state: dir_from("STATE_DIR", dirs::state_dir().or_else(dirs::data_local_dir))?,
+        };
+
+        for dir in [
+            &paths.data,
+            &paths.cache,
+            &paths.cache.join("previews"),
+            &paths.cache.join("lists"),
+            &paths.cache.join("cursors"),
+            &paths.data.join("downloads"),
+            &paths.state,
+        ] {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        Ok(paths)
+    }
+
+    // the directory this project used to require everything to live in
+    pub fn legacy_dir() -> Option<PathBuf> {
+        let mut t = dirs::home_dir()?;
+        t.push("git");
+        t.push(APP_NAME);
+        Some(t)
+    }
+
+    pub fn buf(&self) -> PathBuf {
+        self.cache.join("buf")
+    }
+
+    pub fn entries_cache(&self) -> PathBuf {
+        self.cache.join("entries.json")
+    }
+
+    // what the entries cache held before it was last refetched
+    pub fn previous_entries(&self) -> PathBuf {
+        self.cache.join("entries.previous.json")
+    }
+
+    pub fn selection(&self) -> PathBuf {
+        self.cache.join("title-url-name.txt")
+    }
+
+    // one of a course's listings, e.g. its modules
+    pub fn list_cache(&self, course_id: u64, list: &str) -> PathBuf {
+        self.cache
+            .join("lists")
+            .join(format!("{}-{}.json", course_id, list))
+    }
+
+    // where paging through a listing stopped, named after its first page's
+    // url. a course's listings start with its id, like its other caches
+    pub fn cursor(&self, url: &str) -> PathBuf {
+        let name: String = url
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let course_id = url
+            .split("/courses/")
+            .nth(1)
+            .and_then(|rest| rest.split(['/', '?']).next())
+            .filter(|id| id.parse::<u64>().is_ok());
+        let name = match course_id {
+            Some(id) => format!("{}-{}.json", id, name),
+            None => format!("{}.json", name),
+        };
+        self.cache.join("cursors").join(name)
+    }
+
+    // the cached text of a page, for previews
+    pub fn preview(&self, course_id: &str, slug: &str) -> PathBuf {
+        self.cache
+            .join("previews")
+            .join(format!("{}-{}.txt", course_id, slug))
+    }
+
+    // every cached listing, preview, and cursor, along with the id of the
+    // course it's from
+    pub fn course_caches(&self) -> Result<Vec<(u64, PathBuf)>> {
+        let mut caches = Vec::new();
+        for dir in [
+            self.cache.join("lists"),
+            self.cache.join("previews"),
+            self.cache.join("cursors"),
+        ] {
+            for file in std::fs::read_dir(&dir)
+                .with_context(|| format!("couldn't read {}", dir.display()))?
+            {
+                let path = file?.path();
+                let course_id = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .and_then(|name| name.split_once('-'))
+                    .and_then(|(id, _)| id.parse().ok());
+                if let Some(course_id) = course_id {
+                    caches.push((course_id, path));
+                }
+            }
+        }
+        Ok(caches)
+    }
+
+    pub fn history(&self) -> PathBuf {
+        self.state.join("history.json")
+    }
+
+    // a file downloaded from canvas, kept apart from others of the same name
+    pub fn download(&self, file_id: u64, name: &str) -> PathBuf {
+        self.data
+            .join("downloads")
+            .join(format!("{}-{}", file_id, name))
+    }
+
+    // which urls have been downloaded, and to where
+    pub fn downloads(&self) -> PathBuf {
+        self.state.join("downloads.json")
+    }
+
+    // everything written to the state dir, for `reset-state`, along with the
+    // locks and any temporary files `write_state_atomic` left behind
+    pub fn state_files(&self) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for file in [self.history(), self.downloads()] {
+            files.push(file.with_extension("lock"));
+            let tmp_prefix = file.with_extension("tmp.");
+            let tmp_prefix = tmp_prefix.to_string_lossy();
+            for tmp in std::fs::read_dir(&self.state)
+                .into_iter()
+                .flatten()
+                .flatten()
+            {
+                if tmp
+                    .path()
+                    .to_string_lossy()
+                    .starts_with(tmp_prefix.as_ref())
+                {
+                    files.push(tmp.path());
+                }
+            }
+            files.push(file);
+        }
+        files
+    }
+
+    pub fn sh_script(&self) -> Result<PathBuf> {
+        self.install_script("fzf-to-title-url-name.sh", SH_SCRIPT)
+    }
+
+    pub fn ps1_script(&self) -> Result<PathBuf> {
+        self.install_script("fzf-to-title-url-name.ps1", PS1_SCRIPT)
+    }
+
+    // writes an embedded script into the data dir, unless an identical copy is
+    // already there
+    fn install_script(&self, name: &str, contents: &str) -> Result<PathBuf> {
+        let path = self.data.join(name);
+        if std::fs::read_to_string(&path).ok().as_deref() != Some(contents) {
+            std::fs::write(&path, contents)?;
+        }
+        Ok(path)
+    }
+}
+
+fn dir_from(var: &str, base: Option<PathBuf>) -> Result<PathBuf> {
+    if let Ok(dir) = std::env::var(var) {
+        return Ok(PathBuf::from(dir));
+    }
+    match base {
+        Some(base) => Ok(base.join(APP_NAME)),
+        None => anyhow::bail!("could not determine a default directory, set `{}`", var),
+    }
+}
+
+// rewrites a state file so that overlapping runs (a scheduled `warm` and an
+// interactive search, say) neither leave it half written nor lose each other's
+// changes: while holding a lock, `update` is handed what's in the file now
+// (if anything) and what it returns goes to a temporary file that's renamed
+// over the old one. filesystems without locks just go without, where only
+// torn writes are prevented
+pub fn update_state_atomic(
+    path: &Path,
+    update: impl FnOnce(Option<String>) -> Result<String>,
+) -> Result<()> {
+    let lock_path = path.with_extension("lock");
+    let lock = std::fs::File::create(&lock_path)
+        .with_context(|| format!("couldn't create {}", lock_path.display()))?;
+    match lock.lock() {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::Unsupported => {}
+        Err(e) => return Err(e).with_context(|| format!("couldn't lock {}", lock_path.display())),
+    }
+
+    // read under the lock, so nothing written since this run loaded it is
+    // lost
+    let current = std::fs::read_to_string(path).ok();
+    let contents = update(current)?;
+
+    // unique per process and write, so a writer that's ignoring the lock
+    // can't write into this one's temporary file
+    static WRITES: AtomicUsize = AtomicUsize::new(0);
+    let tmp = path.with_extension(format!(
+        "tmp.{}.{}",
+        std::process::id(),
+        WRITES.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::write(&tmp, contents).with_context(|| format!("couldn't write {}", tmp.display()))?;
+    if let Err(e) = std::fs::rename(&tmp, path) {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e).with_context(|| format!("couldn't replace {}", path.display()));
+    }
+
+    // dropping the file releases the lock
+    drop(lock);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a file of its own in the temp dir, removed along with its lock once
+    // the test is done
+    struct TempState(PathBuf);
+
+    impl TempState {
+        fn new(name: &str) -> Self {
+            Self(std::env::temp_dir().join(format!("cff-{}-{}.json", name, std::process::id())))
+        }
+    }
+
+    impl Drop for TempState {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+            let _ = std::fs::remove_file(self.0.with_extension("lock"));
+        }
+    }
+
+    #[test]
+    fn concurrent_writes_are_never_torn() {
+        let state = TempState::new("torn");
+        let path = &state.0;
+        let writers = 8;
+
+        std::thread::scope(|scope| {
+            for writer in 0..writers {
+                scope.spawn(move || {
+                    // big enough that a torn write would be caught
+                    let contents = serde_json::to_string(&vec![writer; 50_000]).unwrap();
+                    for _ in 0..10 {
+                        update_state_atomic(path, |_| Ok(contents.clone())).unwrap();
+                    }
+                });
+            }
+            scope.spawn(|| {
+                for _ in 0..200 {
+                    let Ok(text) = std::fs::read_to_string(path) else {
+                        continue;
+                    };
+                    let values: Vec<usize> = serde_json::from_str(&text).unwrap();
+                    assert_eq!(values.len(), 50_000);
+                    assert!(values.iter().all(|&value| value == values[0]));
+                }
+            });
+        });
+
+        let values: Vec<usize> =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        assert!(values[0] < writers);
+    }
+
+    #[test]
+    fn concurrent_updates_are_never_lost() {
+        let state = TempState::new("lost");
+        let path = &state.0;
+
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(move || {
+                    for _ in 0..25 {
+                        update_state_atomic(path, |current| {
+                            let count: u32 = current.map_or(0, |s| s.parse().unwrap());
+                            Ok((count + 1).to_string())
+                        })
+                        .unwrap();
+                    }
+                });
+            }
+        });
+
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "200");
+    }
+}
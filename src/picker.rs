@@ -0,0 +1,65 @@
+// a bare bones picker for `--safe`, where no finder can be run: candidates are
+// listed on stderr and narrowed down by whatever's typed on stdin
+
+use std::io::{BufRead, Write};
+
+use anyhow::Result;
+
+// how many candidates are listed at once
+const SHOWN: usize = 20;
+
+// returns the picked line, or an empty string if nothing was picked, the same
+// as the finder scripts leave behind
+pub fn pick(lines: &str, header: Option<&str>) -> Result<String> {
+    let all: Vec<&str> = lines.lines().collect();
+    let mut candidates = all.clone();
+    let stdin = std::io::stdin();
+    let mut stderr = std::io::stderr();
+
+    loop {
+        if let Some(header) = header {
+            writeln!(stderr, "{}", header)?;
+        }
+        for (i, line) in candidates.iter().take(SHOWN).enumerate() {
+            writeln!(stderr, "{:>3}  {}", i + 1, crate::entry::unpad(line))?;
+        }
+        if candidates.len() > SHOWN {
+            writeln!(stderr, "     ... and {} more", candidates.len() - SHOWN)?;
+        }
+        write!(stderr, "number or filter (empty to quit): ")?;
+        stderr.flush()?;
+
+        let mut input = String::new();
+        if stdin.lock().read_line(&mut input)? == 0 {
+            return Ok(String::new());
+        }
+        let input = input.trim();
+        if input.is_empty() {
+            return Ok(String::new());
+        }
+
+        if let Ok(n) = input.parse::<usize>() {
+            match candidates.get(n.wrapping_sub(1)) {
+                Some(line) => return Ok(line.to_string()),
+                None => writeln!(stderr, "no entry {}", n)?,
+            }
+            continue;
+        }
+
+        // every word has to show up somewhere in the line
+        let words: Vec<String> = input.split_whitespace().map(str::to_lowercase).collect();
+        let matches: Vec<&str> = all
+            .iter()
+            .filter(|line| {
+                let line = line.to_lowercase();
+                words.iter().all(|word| line.contains(word))
+            })
+            .copied()
+            .collect();
+        match matches.as_slice() {
+            [] => writeln!(stderr, "nothing matches `{}`", input)?,
+            [line] => return Ok(line.to_string()),
+            _ => candidates = matches,
+        }
+    }
+}
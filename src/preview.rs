@@ -0,0 +1,88 @@
+// helpers for turning a selected page into something readable in fzf's preview
+// window
+
+use crate::entry::Entry;
+
+// the course id and slug of the page an entry is, if it's a page at all
+pub fn page_target(entry: &Entry) -> Option<(String, String)> {
+    if let Some((course_id, slug)) = page_path(&entry.url) {
+        return Some((course_id.to_string(), slug.to_string()));
+    }
+
+    // module items only know their slug, with the course in the url
+    let slug = entry.page_slug.as_ref()?;
+    let (_, rest) = entry.url.split_once("/courses/")?;
+    let course_id = rest.split('/').next()?;
+    Some((course_id.to_string(), slug.clone()))
+}
+
+// pulls the course id and page slug out of a page's html url, e.g.
+// `https://canvas.uni.edu/courses/123/pages/syllabus`
+pub fn page_path(url: &str) -> Option<(&str, &str)> {
+    let (_, rest) = url.split_once("/courses/")?;
+    let (course_id, rest) = rest.split_once("/pages/")?;
+    let slug = rest.split(['?', '#']).next()?;
+    if course_id.is_empty() || slug.is_empty() || course_id.contains('/') || slug.contains('/') {
+        return None;
+    }
+    Some((course_id, slug))
+}
+
+// a rough html to text conversion, good enough to skim a page in a terminal
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut tag = String::new();
+    let mut in_tag = false;
+
+    for c in html.chars() {
+        match c {
+            '<' => {
+                in_tag = true;
+                tag.clear();
+            }
+            '>' if in_tag => {
+                in_tag = false;
+                // break lines on block level elements
+                let name = tag
+                    .trim_start_matches('/')
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or("")
+                    .to_lowercase();
+                if matches!(
+                    name.as_str(),
+                    "p" | "br" | "div" | "li" | "tr" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6"
+                ) {
+                    text.push('\n');
+                }
+            }
+            _ if in_tag => tag.push(c),
+            _ => text.push(c),
+        }
+    }
+
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+
+    // collapse the runs of blank lines left behind by nested elements
+    let mut out = String::new();
+    let mut blank = true;
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() {
+            if !blank {
+                out.push('\n');
+            }
+            blank = true;
+        } else {
+            out.push_str(line);
+            out.push('\n');
+            blank = false;
+        }
+    }
+    out
+}
@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use reqwest::{Client, Request, Response, ResponseBuilderExt, Url};
+use serde::{Deserialize, Serialize};
+
+// what's put in place of the token wherever it shows up in a recording
+const REDACTED: &str = "[REDACTED]";
+
+// one request and what canvas sent back. the body is kept in a file next to
+// it, since downloads aren't text
+#[derive(Debug, Serialize, Deserialize)]
+struct Interaction {
+    method: String,
+    url: String,
+    // tells apart requests to the same url, like every course's graphql
+    // query, which can finish in any order
+    #[serde(default)]
+    body_hash: Option<String>,
+    status: u16,
+    headers: Vec<(String, String)>,
+}
+
+// what a recorded response is looked up by: the method, url, and a hash of
+// the body
+type Key = (String, String, Option<String>);
+
+// `--record` saves every response into a directory as it's received, and
+// `--replay` answers requests from one instead of the network, so a run can
+// be reproduced exactly somewhere else
+#[derive(Debug)]
+pub enum Session {
+    Record {
+        dir: PathBuf,
+        token: String,
+        count: AtomicUsize,
+        // `MAX_RESPONSE_MB`, since the whole body is read to save it
+        max_bytes: u64,
+    },
+    Replay {
+        dir: PathBuf,
+        // responses to each method, url, and body, in the order they were
+        // recorded
+        responses: Mutex<HashMap<Key, Vec<usize>>>,
+    },
+}
+
+impl Session {
+    pub fn record(dir: &Path, token: &str, max_bytes: u64) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("couldn't create {}", dir.display()))?;
+        Ok(Session::Record {
+            dir: dir.to_path_buf(),
+            token: token.to_string(),
+            count: AtomicUsize::new(0),
+            max_bytes,
+        })
+    }
+
+    pub fn replay(dir: &Path) -> Result<Self> {
+        let mut responses: HashMap<_, Vec<usize>> = HashMap::new();
+        for i in 0.. {
+            let path = interaction_path(dir, i);
+            if !path.exists() {
+                break;
+            }
+            let interaction = read_interaction(&path)?;
+            responses
+                .entry((interaction.method, interaction.url, interaction.body_hash))
+                .or_default()
+                .push(i);
+        }
+        if responses.is_empty() {
+            anyhow::bail!("nothing was recorded into {}", dir.display());
+        }
+
+        Ok(Session::Replay {
+            dir: dir.to_path_buf(),
+            responses: Mutex::new(responses),
+        })
+    }
+
+    // sends a request, or answers it from the recording
+    pub async fn execute(&self, client: &Client, request: Request) -> Result<Response> {
+        match self {
+            Session::Record {
+                dir,
+                token,
+                count,
+                max_bytes,
+            } => {
+                let method = request.method().to_string();
+                let url = request.url().to_string();
+                let body_hash = body_hash(&request);
+                let res = client.execute(request).await?;
+
+                let i = count.fetch_add(1, Ordering::SeqCst);
+                let redact = |s: &str| match token.is_empty() {
+                    true => s.to_string(),
+                    false => s.replace(token.as_str(), REDACTED),
+                };
+                let interaction = Interaction {
+                    method,
+                    url: redact(&url),
+                    body_hash,
+                    status: res.status().as_u16(),
+                    // the body's length can change with redaction
+                    headers: res
+                        .headers()
+                        .iter()
+                        .filter(|(name, _)| {
+                            *name != reqwest::header::CONTENT_LENGTH
+                                && *name != reqwest::header::TRANSFER_ENCODING
+                        })
+                        .map(|(name, value)| {
+                            (
+                                name.to_string(),
+                                redact(&String::from_utf8_lossy(value.as_bytes())),
+                            )
+                        })
+                        .collect(),
+                };
+                let body = redact_bytes(
+                    &crate::http::read_body(res, *max_bytes).await?,
+                    token.as_bytes(),
+                );
+
+                let path = interaction_path(dir, i);
+                std::fs::write(&path, serde_json::to_string_pretty(&interaction)?)
+                    .with_context(|| format!("couldn't write {}", path.display()))?;
+                std::fs::write(path.with_extension("body"), &body)?;
+
+                to_response(&interaction, body)
+            }
+            Session::Replay { dir, responses } => {
+                let key = (
+                    request.method().to_string(),
+                    request.url().to_string(),
+                    body_hash(&request),
+                );
+                // the same request made more than it was recorded gets the
+                // last answer again
+                let i = {
+                    let mut responses = responses.lock().unwrap();
+                    let Some(recorded) = responses.get_mut(&key) else {
+                        anyhow::bail!("{} {} wasn't recorded in {}", key.0, key.1, dir.display());
+                    };
+                    if recorded.len() > 1 {
+                        recorded.remove(0)
+                    } else {
+                        recorded[0]
+                    }
+                };
+
+                let path = interaction_path(dir, i);
+                let interaction = read_interaction(&path)?;
+                let body = std::fs::read(path.with_extension("body"))
+                    .with_context(|| format!("couldn't read the body for {}", path.display()))?;
+                to_response(&interaction, body)
+            }
+        }
+    }
+}
+
+// fnv-1a, since it has to come out the same wherever the recording is
+// replayed
+fn body_hash(request: &Request) -> Option<String> {
+    let body = request.body()?.as_bytes()?;
+    let hash = body.iter().fold(0xcbf29ce484222325u64, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    Some(format!("{:016x}", hash))
+}
+
+fn interaction_path(dir: &Path, i: usize) -> PathBuf {
+    dir.join(format!("{:05}.json", i))
+}
+
+fn read_interaction(path: &Path) -> Result<Interaction> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("couldn't read {}", path.display()))?;
+    serde_json::from_str(&text).with_context(|| format!("invalid recording {}", path.display()))
+}
+
+fn to_response(interaction: &Interaction, body: Vec<u8>) -> Result<Response> {
+    let mut builder = ::http::Response::builder()
+        .status(interaction.status)
+        .url(Url::parse(&interaction.url)?);
+    for (name, value) in &interaction.headers {
+        builder = builder.header(name, value);
+    }
+    Ok(Response::from(builder.body(body)?))
+}
+
+fn redact_bytes(body: &[u8], token: &[u8]) -> Vec<u8> {
+    if token.is_empty() {
+        return body.to_vec();
+    }
+    let mut out = Vec::with_capacity(body.len());
+    let mut rest = body;
+    while !rest.is_empty() {
+        if rest.starts_with(token) {
+            out.extend_from_slice(REDACTED.as_bytes());
+            rest = &rest[token.len()..];
+        } else {
+            out.push(rest[0]);
+            rest = &rest[1..];
+        }
+    }
+    out
+}
@@ -0,0 +1,80 @@
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use futures::{Stream, StreamExt};
+use tokio::io::AsyncWriteExt;
+use tokio::net::unix::pipe;
+
+use crate::Config;
+
+// opens the finder the same way as usual, but with the buffer as a named
+// pipe, so that it can start on `seed` and be handed more lines from `lines`
+// for as long as it's open. the finder takes whatever comes in without
+// needing to be restarted
+pub async fn fuzzy_finder(
+    config: &Config,
+    finder: &Path,
+    seed: &str,
+    fzf_args: &[String],
+    lines: impl Stream<Item = String>,
+) -> Result<String> {
+    let paths = &config.paths;
+    let buf = paths.buf();
+
+    let _ = std::fs::remove_file(&buf);
+    let status = std::process::Command::new("mkfifo")
+        .arg(&buf)
+        .status()
+        .context("couldn't run mkfifo")?;
+    if !status.success() {
+        anyhow::bail!("couldn't make a pipe at {}", buf.display());
+    }
+
+    crate::clear_selection(config)?;
+
+    let mut terminal = tokio::process::Command::new("kitty")
+        .env("TOKEN", &config.token)
+        .arg("sh")
+        .arg(paths.sh_script()?)
+        .arg(&buf)
+        .arg(paths.selection())
+        .arg(finder)
+        .args(fzf_args)
+        .spawn()
+        .context("couldn't open the finder")?;
+
+    let feed = async {
+        // the pipe can't be opened until the finder's on the other end of it
+        let mut sender = loop {
+            match pipe::OpenOptions::new().open_sender(&buf) {
+                Ok(sender) => break sender,
+                Err(e) if e.raw_os_error() == Some(ENXIO) => {
+                    tokio::time::sleep(Duration::from_millis(20)).await
+                }
+                Err(e) => return Err(e),
+            }
+        };
+        sender.write_all(seed.as_bytes()).await?;
+        tokio::pin!(lines);
+        while let Some(line) = lines.next().await {
+            sender.write_all(line.as_bytes()).await?;
+        }
+        // closing it tells the finder nothing else is coming
+        Ok(())
+    };
+
+    // the finder closing its end, once something's picked, is the usual way
+    // for this to stop. a pipe left behind would hang the next run that
+    // writes the buffer as a file
+    let status = tokio::select! {
+        status = terminal.wait() => status?,
+        _ = feed => terminal.wait().await?,
+    };
+    let _ = std::fs::remove_file(&buf);
+
+    crate::read_selection(config, status)
+}
+
+// what opening a pipe for writing fails with while nothing's reading it
+const ENXIO: i32 = 6;